@@ -0,0 +1,290 @@
+use crate::definition::def_types::*;
+use crate::definition::{get_definition_path, subroutine_calls};
+use crate::server::LSPServer;
+use crate::sources::LSPSupport;
+use log::debug;
+use tower_lsp::lsp_types::*;
+
+/// a subroutine call, with the hierarchical name of the callee and the range of its identifier
+struct CallSite {
+    url: Url,
+    path: Vec<String>,
+    byte_idx: usize,
+    range: Range,
+}
+
+impl LSPServer {
+    pub fn prepare_call_hierarchy(
+        &self,
+        params: CallHierarchyPrepareParams,
+    ) -> Option<Vec<CallHierarchyItem>> {
+        let doc = params.text_document_position_params.text_document.uri;
+        let pos = params.text_document_position_params.position;
+        let file_id = self.srcs.get_id(&doc).to_owned();
+        self.srcs.wait_parse_ready(file_id, false);
+        let file = self.srcs.get_file(file_id)?;
+        let file = file.read().ok()?;
+        let path = get_definition_path(file.text.line(pos.line as usize), pos);
+        debug!("prepare call hierarchy, path: {:?}", &path);
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let def = scope_tree
+            .as_ref()?
+            .find_path(&path, file.text.pos_to_byte(&pos), &doc)?;
+        if def.def_type() != DefinitionType::Subroutine {
+            return None;
+        }
+        Some(vec![self.call_item(def)?])
+    }
+
+    pub fn incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> Option<Vec<CallHierarchyIncomingCall>> {
+        debug!("incoming calls: {}", &params.item.name);
+        let sites = self.call_sites(None);
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let scope_tree = scope_tree.as_ref()?;
+        let target = self.item_scope(scope_tree, &params.item)?;
+        let mut calls: Vec<(&dyn Scope, Vec<Range>)> = Vec::new();
+        for site in sites {
+            match scope_tree.find_path(&site.path, site.byte_idx, &site.url) {
+                Some(callee) if same_def(callee, target) => (),
+                _ => continue,
+            }
+            let caller = match caller(scope_tree, &site.url, site.byte_idx) {
+                Some(caller) => caller,
+                None => continue,
+            };
+            match calls.iter_mut().find(|x| same_def(x.0, caller)) {
+                Some(call) => call.1.push(site.range),
+                None => calls.push((caller, vec![site.range])),
+            }
+        }
+        Some(
+            calls
+                .into_iter()
+                .filter_map(|(caller, from_ranges)| {
+                    Some(CallHierarchyIncomingCall {
+                        from: self.call_item(caller)?,
+                        from_ranges,
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    pub fn outgoing_calls(
+        &self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> Option<Vec<CallHierarchyOutgoingCall>> {
+        debug!("outgoing calls: {}", &params.item.name);
+        let sites = self.call_sites(Some(&params.item.uri));
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let scope_tree = scope_tree.as_ref()?;
+        let source = self.item_scope(scope_tree, &params.item)?;
+        let mut calls: Vec<(&dyn Definition, Vec<Range>)> = Vec::new();
+        for site in sites {
+            if site.byte_idx < source.start() || site.byte_idx > source.end() {
+                continue;
+            }
+            let callee = match scope_tree.find_path(&site.path, site.byte_idx, &site.url) {
+                Some(callee) if callee.def_type() == DefinitionType::Subroutine => callee,
+                _ => continue,
+            };
+            match calls.iter_mut().find(|x| same_def(x.0, callee)) {
+                Some(call) => call.1.push(site.range),
+                None => calls.push((callee, vec![site.range])),
+            }
+        }
+        Some(
+            calls
+                .into_iter()
+                .filter_map(|(callee, from_ranges)| {
+                    Some(CallHierarchyOutgoingCall {
+                        to: self.call_item(callee)?,
+                        from_ranges,
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// all subroutine calls in the indexed files, or only those in the file with the given url
+    fn call_sites(&self, uri: Option<&Url>) -> Vec<CallSite> {
+        let mut sites: Vec<CallSite> = Vec::new();
+        let files = match self.srcs.files.read() {
+            Ok(files) => files.clone(),
+            Err(_) => return sites,
+        };
+        for file in files {
+            let file = match file.read() {
+                Ok(file) => file,
+                Err(_) => continue,
+            };
+            if uri.is_some() && uri != Some(&file.uri) {
+                continue;
+            }
+            let syntax_tree = match &file.syntax_tree {
+                Some(syntax_tree) => syntax_tree,
+                None => continue,
+            };
+            for (ident, byte_idx) in subroutine_calls(syntax_tree) {
+                let start = file.text.byte_to_pos(byte_idx);
+                let end = file.text.byte_to_pos(byte_idx + ident.len());
+                sites.push(CallSite {
+                    url: file.uri.clone(),
+                    path: get_definition_path(file.text.line(start.line as usize), start),
+                    byte_idx,
+                    range: Range::new(start, end),
+                });
+            }
+        }
+        sites
+    }
+
+    /// find the scope a call hierarchy item was created from
+    fn item_scope<'a>(
+        &self,
+        scope_tree: &'a GenericScope,
+        item: &CallHierarchyItem,
+    ) -> Option<&'a dyn Scope> {
+        let byte_idx = self
+            .srcs
+            .pos_to_byte(&item.uri, &item.selection_range.start)?;
+        scope_tree.scope_at(&item.uri, byte_idx)
+    }
+
+    fn call_item(&self, def: &dyn Definition) -> Option<CallHierarchyItem> {
+        let (range, selection_range) = self.srcs.def_ranges(def)?;
+        Some(CallHierarchyItem {
+            name: def.ident(),
+            kind: def.symbol_kind(),
+            tags: None,
+            detail: Some(clean_type_str(&def.type_str(), &def.ident())),
+            uri: def.url(),
+            range,
+            selection_range,
+            data: None,
+        })
+    }
+}
+
+fn same_def(a: &dyn Definition, b: &dyn Definition) -> bool {
+    a.byte_idx() == b.byte_idx() && a.url() == b.url()
+}
+
+/// the innermost subroutine containing a call, or for calls outside of any subroutine, the
+/// module, class or package containing it
+fn caller<'a>(scope_tree: &'a GenericScope, url: &Url, byte_idx: usize) -> Option<&'a dyn Scope> {
+    let mut containing: Vec<&dyn Scope> = Vec::new();
+    let mut scopes = scope_tree.scopes();
+    while let Some(scope) = scopes
+        .iter()
+        .find(|x| x.url() == *url && x.start() <= byte_idx && byte_idx <= x.end())
+    {
+        containing.push(scope.as_ref());
+        scopes = scope.scopes();
+    }
+    containing
+        .iter()
+        .rev()
+        .find(|x| x.def_type() == DefinitionType::Subroutine)
+        .or_else(|| containing.first())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::test_init;
+
+    #[test]
+    fn test_call_hierarchy() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let text = r#"class driver;
+  function void drive(int value);
+    log(value);
+  endfunction
+  function void log(int value);
+  endfunction
+endclass
+module test;
+  driver drv;
+  task automatic run();
+    drv.drive(1);
+    drv.drive(2);
+  endtask
+  initial begin
+    drv.drive(3);
+    run();
+  end
+endmodule
+"#;
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+        let fid = server.srcs.get_id(&uri);
+        server.srcs.wait_parse_ready(fid, true);
+        let prepare = |position: Position| {
+            server
+                .prepare_call_hierarchy(CallHierarchyPrepareParams {
+                    text_document_position_params: TextDocumentPositionParams {
+                        text_document: TextDocumentIdentifier { uri: uri.clone() },
+                        position,
+                    },
+                    work_done_progress_params: WorkDoneProgressParams::default(),
+                })
+                .unwrap()
+                .remove(0)
+        };
+
+        let drive = prepare(Position::new(1, 17));
+        assert_eq!(drive.name, "drive");
+        assert_eq!(drive.selection_range.start, Position::new(1, 16));
+        let incoming = server
+            .incoming_calls(CallHierarchyIncomingCallsParams {
+                item: drive.clone(),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .unwrap();
+        let callers: Vec<(&str, usize)> = incoming
+            .iter()
+            .map(|x| (x.from.name.as_str(), x.from_ranges.len()))
+            .collect();
+        assert_eq!(callers, vec![("run", 2), ("test", 1)]);
+        assert_eq!(
+            incoming[0].from_ranges[0],
+            Range::new(Position::new(10, 8), Position::new(10, 13))
+        );
+
+        let outgoing = server
+            .outgoing_calls(CallHierarchyOutgoingCallsParams {
+                item: drive,
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .unwrap();
+        let callees: Vec<&str> = outgoing.iter().map(|x| x.to.name.as_str()).collect();
+        assert_eq!(callees, vec!["log"]);
+        assert_eq!(outgoing[0].to.selection_range.start, Position::new(4, 16));
+
+        // variables aren't part of the call hierarchy
+        assert!(server
+            .prepare_call_hierarchy(CallHierarchyPrepareParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position: Position::new(8, 10),
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            })
+            .is_none());
+    }
+}
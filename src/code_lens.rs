@@ -0,0 +1,260 @@
+use crate::definition::def_types::*;
+use crate::definition::{get_definition_path, matching_identifiers};
+use crate::hierarchy::instances;
+use crate::server::LSPServer;
+use crate::sources::LSPSupport;
+use log::debug;
+use serde_json::json;
+use std::collections::HashSet;
+use tower_lsp::lsp_types::*;
+
+impl LSPServer {
+    pub fn code_lens(&self, params: CodeLensParams) -> Option<Vec<CodeLens>> {
+        let uri = params.text_document.uri;
+        debug!("code lens: {}", &uri);
+        let file_id = self.srcs.get_id(&uri).to_owned();
+        self.srcs.wait_parse_ready(file_id, false);
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let scope_tree = scope_tree.as_ref()?;
+        let mut lenses: Vec<CodeLens> = Vec::new();
+
+        // modules, interfaces and programs, with their instantiations
+        let insts: Vec<&dyn Definition> = scope_tree
+            .scopes()
+            .iter()
+            .flat_map(|x| instances(x.as_ref()))
+            .collect();
+        for unit in scope_tree
+            .scopes()
+            .iter()
+            .filter(|x| x.url() == uri && is_design_unit(x.as_ref()))
+        {
+            let locations: Vec<Location> = insts
+                .iter()
+                .filter(|x| x.mod_ident() == Some(unit.ident()))
+                .filter_map(|x| Some(Location::new(x.url(), self.srcs.def_ranges(*x)?.1)))
+                .collect();
+            lenses.extend(self.lens(unit.as_ref(), locations, "instantiation"));
+        }
+
+        // functions, tasks and classes, with their references
+        let mut targets: Vec<&dyn Definition> = Vec::new();
+        for scope in scope_tree.scopes() {
+            if scope.url() == uri {
+                referenceable(scope.as_ref(), &mut targets);
+            }
+        }
+        for (target, locations) in targets.iter().zip(self.references(scope_tree, &targets)) {
+            lenses.extend(self.lens(*target, locations, "reference"));
+        }
+        lenses.sort_by_key(|x| x.range.start);
+        Some(lenses)
+    }
+
+    /// the references to each of the targets in the indexed files, excluding the identifiers of
+    /// the targets themselves
+    fn references(
+        &self,
+        scope_tree: &GenericScope,
+        targets: &[&dyn Definition],
+    ) -> Vec<Vec<Location>> {
+        let mut references: Vec<Vec<Location>> = vec![Vec::new(); targets.len()];
+        let names: HashSet<String> = targets.iter().map(|x| x.ident()).collect();
+        let files = match self.srcs.files.read() {
+            Ok(files) => files.clone(),
+            Err(_) => return references,
+        };
+        for file in files {
+            let file = match file.read() {
+                Ok(file) => file,
+                Err(_) => continue,
+            };
+            let syntax_tree = match &file.syntax_tree {
+                Some(syntax_tree) => syntax_tree,
+                None => continue,
+            };
+            for (ident, byte_idx) in matching_identifiers(syntax_tree, |x| names.contains(x)) {
+                if targets
+                    .iter()
+                    .any(|x| x.url() == file.uri && x.byte_idx() == byte_idx)
+                {
+                    continue;
+                }
+                let start = file.text.byte_to_pos(byte_idx);
+                let path = get_definition_path(file.text.line(start.line as usize), start);
+                let def = match scope_tree.find_path(&path, byte_idx, &file.uri) {
+                    Some(def) => def,
+                    None => continue,
+                };
+                if let Some(idx) = targets
+                    .iter()
+                    .position(|x| x.byte_idx() == def.byte_idx() && x.url() == def.url())
+                {
+                    let end = file.text.byte_to_pos(byte_idx + ident.len());
+                    references[idx].push(Location::new(file.uri.clone(), Range::new(start, end)));
+                }
+            }
+        }
+        references
+    }
+
+    /// a lens showing the number of locations, which runs the configured command with the
+    /// locations when clicked
+    fn lens(&self, def: &dyn Definition, locations: Vec<Location>, noun: &str) -> Option<CodeLens> {
+        let (_, range) = self.srcs.def_ranges(def)?;
+        let title = match locations.len() {
+            1 => format!("1 {}", noun),
+            n => format!("{} {}s", n, noun),
+        };
+        // the commands to show a list of locations are client specific
+        let command = self.conf.read().ok()?.code_lens_command.clone();
+        let command = match command {
+            Some(command) => Command {
+                title,
+                command,
+                arguments: Some(vec![json!(def.url()), json!(range.start), json!(locations)]),
+            },
+            None => Command {
+                title,
+                command: String::new(),
+                arguments: None,
+            },
+        };
+        Some(CodeLens {
+            range,
+            command: Some(command),
+            data: None,
+        })
+    }
+}
+
+/// the functions, tasks and classes within a scope, including the scope itself. Method bodies
+/// defined outside of their class are referenced through their prototype, and constructors
+/// are called through the new keyword rather than by name
+fn referenceable<'a>(scope: &'a dyn Scope, targets: &mut Vec<&'a dyn Definition>) {
+    match scope.def_type() {
+        DefinitionType::Subroutine
+            if scope.class_scope().is_none() && scope.symbol_kind() != SymbolKind::CONSTRUCTOR =>
+        {
+            targets.push(scope)
+        }
+        DefinitionType::Class => targets.push(scope),
+        _ => (),
+    }
+    for child in scope.scopes() {
+        referenceable(child.as_ref(), targets);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::test_init;
+
+    #[test]
+    fn test_code_lens() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let uri2 = Url::parse("file:///top.sv").unwrap();
+        let text = r#"class packet;
+  function int size();
+    return 1;
+  endfunction
+endclass
+module adder;
+  function int add(int a, int b);
+    return a + b;
+  endfunction
+  initial $display(add(1, 2));
+endmodule
+module unused;
+endmodule
+"#;
+        let text2 = r#"module top;
+  adder u_adder0 ();
+  adder u_adder1 ();
+  packet pkt;
+  initial $display(pkt.size());
+endmodule
+"#;
+        for (uri, text) in &[(&uri, text), (&uri2, text2)] {
+            server.did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: (*uri).clone(),
+                    language_id: "systemverilog".to_owned(),
+                    version: 0,
+                    text: text.to_string(),
+                },
+            });
+            let fid = server.srcs.get_id(uri);
+            server.srcs.wait_parse_ready(fid, true);
+        }
+        let lenses = server
+            .code_lens(CodeLensParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .unwrap();
+        let titles: Vec<(u32, &str)> = lenses
+            .iter()
+            .map(|x| {
+                (
+                    x.range.start.line,
+                    x.command.as_ref().unwrap().title.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                (0, "1 reference"),
+                (1, "1 reference"),
+                (5, "2 instantiations"),
+                (6, "1 reference"),
+                (11, "0 instantiations"),
+            ]
+        );
+        // without a configured command, lenses only show the count
+        assert!(lenses.iter().all(|x| {
+            let command = x.command.as_ref().unwrap();
+            command.command.is_empty() && command.arguments.is_none()
+        }));
+        server.conf.write().unwrap().code_lens_command =
+            Some("editor.action.showReferences".to_owned());
+        let lenses = server
+            .code_lens(CodeLensParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .unwrap();
+        assert_eq!(
+            lenses[2].command.as_ref().unwrap().command,
+            "editor.action.showReferences"
+        );
+        // the lens for adder lists the instantiations in top
+        let arguments = lenses[2]
+            .command
+            .as_ref()
+            .unwrap()
+            .arguments
+            .as_ref()
+            .unwrap();
+        let locations: Vec<Location> = serde_json::from_value(arguments[2].clone()).unwrap();
+        assert_eq!(
+            locations,
+            vec![
+                Location::new(
+                    uri2.clone(),
+                    Range::new(Position::new(1, 8), Position::new(1, 16))
+                ),
+                Location::new(
+                    uri2.clone(),
+                    Range::new(Position::new(2, 8), Position::new(2, 16))
+                ),
+            ]
+        );
+    }
+}
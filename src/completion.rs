@@ -1,11 +1,26 @@
+use crate::definition::def_types::*;
+use crate::definition::{def_hover, macro_definition, text_macro_defs};
+use crate::include::{includes, resolve_include};
 use crate::server::LSPServer;
 use crate::sources::LSPSupport;
 use log::{debug, trace};
+use regex::{Match, Regex};
 use ropey::{Rope, RopeSlice};
 use std::time::Instant;
 use tower_lsp::lsp_types::*;
 
+mod instantiation;
+use instantiation::{
+    at_module_item, instantiation_completion, instantiation_list, instantiation_members,
+};
 pub mod keyword;
+mod syntax_context;
+use syntax_context::{keyword_allowed, syntax_context};
+mod text_macro;
+use text_macro::macro_completion;
+
+/// the most identifier completions returned at once
+const MAX_COMPLETIONS: usize = 1000;
 
 impl LSPServer {
     pub fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
@@ -24,6 +39,17 @@ impl LSPServer {
             file.text.line(doc.position.line as usize),
             doc.position,
         );
+        // the path of an `include directive
+        if let Some(response) =
+            self.include_completion(&file.text, &doc.text_document.uri, doc.position)
+        {
+            return Some(CompletionResponse::List(response));
+        }
+        // the members of a package or class, after `pkg::`
+        if let Some(path) = scope_path(&file.text, doc.position, &token) {
+            let response = self.srcs.get_scoped_completions(&path, &token)?;
+            return Some(CompletionResponse::List(response));
+        }
         let response = match params.context {
             Some(context) => match context.trigger_kind {
                 CompletionTriggerKind::TRIGGER_CHARACTER => {
@@ -32,79 +58,433 @@ impl LSPServer {
                         context.trigger_character.clone()?.as_str()
                     );
                     match context.trigger_character?.as_str() {
-                        "." => Some(self.srcs.get_dot_completions(
-                            token.trim_end_matches('.'),
-                            file.text.pos_to_byte(&doc.position),
+                        "." => self.dot_completion(
+                            &file.text,
+                            &token,
+                            doc.position,
                             &doc.text_document.uri,
-                        )?),
+                        ),
                         "$" => Some(CompletionList {
                             is_incomplete: false,
                             items: self.sys_tasks.clone(),
                         }),
-                        "`" => Some(CompletionList {
-                            is_incomplete: false,
-                            items: self.directives.clone(),
-                        }),
+                        "`" => self.directive_completion(&file.text, &doc.text_document.uri),
                         _ => None,
                     }
                 }
-                CompletionTriggerKind::TRIGGER_FOR_INCOMPLETE_COMPLETIONS => None,
-                CompletionTriggerKind::INVOKED => {
+                CompletionTriggerKind::INVOKED
+                | CompletionTriggerKind::TRIGGER_FOR_INCOMPLETE_COMPLETIONS => {
                     debug!("Invoked Completion");
-                    let mut comps = self.srcs.get_completions(
+                    self.identifier_completion(
+                        &file.text,
                         &token,
-                        file.text.pos_to_byte(&doc.position),
+                        doc.position,
                         &doc.text_document.uri,
-                    )?;
-                    // complete keywords
-                    comps.items.extend::<Vec<CompletionItem>>(
-                        self.key_comps
-                            .iter()
-                            .filter(|x| x.label.starts_with(&token))
-                            .cloned()
-                            .collect(),
-                    );
-                    Some(comps)
+                    )
                 }
                 _ => None,
             },
             None => {
                 let trigger = prev_char(&file.text, &doc.position);
                 match trigger {
-                    '.' => Some(self.srcs.get_dot_completions(
-                        token.trim_end_matches('.'),
-                        file.text.pos_to_byte(&doc.position),
+                    '.' => self.dot_completion(
+                        &file.text,
+                        &token,
+                        doc.position,
                         &doc.text_document.uri,
-                    )?),
+                    ),
                     '$' => Some(CompletionList {
                         is_incomplete: false,
                         items: self.sys_tasks.clone(),
                     }),
-                    '`' => Some(CompletionList {
-                        is_incomplete: false,
-                        items: self.directives.clone(),
-                    }),
-                    _ => {
-                        let mut comps = self.srcs.get_completions(
-                            &token,
-                            file.text.pos_to_byte(&doc.position),
-                            &doc.text_document.uri,
-                        )?;
-                        comps.items.extend::<Vec<CompletionItem>>(
-                            self.key_comps
-                                .iter()
-                                .filter(|x| x.label.starts_with(&token))
-                                .cloned()
-                                .collect(),
-                        );
-                        Some(comps)
-                    }
+                    '`' => self.directive_completion(&file.text, &doc.text_document.uri),
+                    _ => self.identifier_completion(
+                        &file.text,
+                        &token,
+                        doc.position,
+                        &doc.text_document.uri,
+                    ),
                 }
             }
         };
         // eprintln!("comp response: {}", now.elapsed().as_millis());
         Some(CompletionResponse::List(response?))
     }
+
+    /// the compiler directives and the macros defined in the file, the files it includes and
+    /// the rest of the project, preferring the closest definition of a macro
+    fn directive_completion(&self, text: &Rope, uri: &Url) -> Option<CompletionList> {
+        let mut sources: Vec<(Url, Rope)> = vec![(uri.clone(), text.clone())];
+        let include_dirs = self.srcs.include_dirs.read().ok()?.clone();
+        let mut idx = 0;
+        while idx < sources.len() {
+            for inc in includes(&sources[idx].1) {
+                let path = match resolve_include(&inc.path, &sources[idx].0, &include_dirs) {
+                    Ok(path) => path,
+                    Err(_) => continue,
+                };
+                let inc_uri = match Url::from_file_path(&path) {
+                    Ok(inc_uri) => inc_uri,
+                    Err(_) => continue,
+                };
+                if sources.iter().any(|x| x.0 == inc_uri) {
+                    continue;
+                }
+                let inc_text = match self.srcs.get_text(&inc_uri) {
+                    Some(inc_text) => inc_text,
+                    None => match std::fs::read_to_string(&path) {
+                        Ok(inc_text) => Rope::from_str(&inc_text),
+                        Err(_) => continue,
+                    },
+                };
+                sources.push((inc_uri, inc_text));
+            }
+            idx += 1;
+        }
+        let mut items: Vec<CompletionItem> = Vec::new();
+        let add = |items: &mut Vec<CompletionItem>, text: &Rope, def: &dyn Definition| {
+            if !items.iter().any(|x| x.label == def.ident()) {
+                let definition = macro_definition(text, def.byte_idx());
+                items.push(macro_completion(
+                    &def.ident(),
+                    def.completion_kind(),
+                    &definition,
+                ));
+            }
+        };
+        for (url, text) in &sources {
+            for def in text_macro_defs(text, url) {
+                add(&mut items, text, &def);
+            }
+        }
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        for def in scope_tree.as_ref().map_or(&Vec::new(), |x| x.defs()) {
+            if def.def_type() != DefinitionType::Macro || sources.iter().any(|x| x.0 == def.url()) {
+                continue;
+            }
+            if let Some(text) = self.srcs.get_text(&def.url()) {
+                add(&mut items, &text, def.as_ref());
+            }
+        }
+        items.extend(self.directives.iter().cloned());
+        Some(CompletionList {
+            is_incomplete: false,
+            items,
+        })
+    }
+
+    /// attach documentation to a completion item: the declaration of its definition with any
+    /// leading comments, and where it's defined
+    pub fn completion_resolve(&self, mut item: CompletionItem) -> CompletionItem {
+        debug!("completion resolve: {}", &item.label);
+        if let Some(documentation) = self.completion_documentation(&item) {
+            item.documentation = Some(documentation);
+        }
+        item
+    }
+
+    fn completion_documentation(&self, item: &CompletionItem) -> Option<Documentation> {
+        let data: CompletionData = serde_json::from_value(item.data.clone()?).ok()?;
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let scope_tree = scope_tree.as_ref()?;
+        let (def, scopes) = scope_tree.locate(&data.uri, data.byte_idx)?;
+        let text = self.srcs.get_text(&data.uri)?;
+        let file = data
+            .uri
+            .path_segments()
+            .and_then(|mut x| x.next_back())
+            .unwrap_or_default()
+            .to_owned();
+        let location = match scopes.is_empty() {
+            true => format!("`{}`", file),
+            false => {
+                let scopes: Vec<String> = scopes.iter().map(|x| x.ident()).collect();
+                format!("`{}` in `{}`", scopes.join("."), file)
+            }
+        };
+        Some(Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!(
+                "```systemverilog\n{}\n```\n---\ndefined in {}",
+                def_hover(scope_tree, def, &text).trim_end(),
+                location
+            ),
+        }))
+    }
+
+    /// complete the ports or parameters of a module instantiation being written, otherwise the
+    /// members of what precedes the dot
+    fn dot_completion(
+        &self,
+        text: &Rope,
+        token: &str,
+        pos: Position,
+        uri: &Url,
+    ) -> Option<CompletionList> {
+        let byte_idx = text.pos_to_byte(&pos);
+        {
+            let scope_tree = self.srcs.scope_tree.read().ok()?;
+            let scope_tree = scope_tree.as_ref()?;
+            // the instantiation is within the enclosing scope and ends at the next semicolon
+            let start = text.byte_to_char(context_start(scope_tree, uri, byte_idx).min(byte_idx));
+            let cursor = text.byte_to_char(byte_idx);
+            let end = text
+                .chars_at(cursor)
+                .position(|x| x == ';')
+                .map_or(text.len_chars(), |x| cursor + x + 1);
+            let code = text.slice(start..end).to_string();
+            let inst_list = instantiation_list(&code, byte_idx - text.char_to_byte(start));
+            if let Some((module, list, connected)) = inst_list {
+                if let Some(module) = scope_tree
+                    .scopes()
+                    .iter()
+                    .find(|x| x.ident() == module && is_design_unit(x.as_ref()))
+                {
+                    return Some(CompletionList {
+                        is_incomplete: false,
+                        items: instantiation_members(module.as_ref(), list, &connected),
+                    });
+                }
+            }
+        }
+        self.srcs
+            .get_dot_completions(token.trim_end_matches('.'), byte_idx, uri)
+    }
+
+    /// complete identifiers and keywords, offering the literals of an expected enum type first
+    fn identifier_completion(
+        &self,
+        text: &Rope,
+        token: &str,
+        pos: Position,
+        uri: &Url,
+    ) -> Option<CompletionList> {
+        let byte_idx = text.pos_to_byte(&pos);
+        // the start of the enclosing scope, bounding the scans for the syntactic context
+        let end = text
+            .byte_to_char(byte_idx)
+            .saturating_sub(token.chars().count());
+        let start = {
+            let scope_tree = self.srcs.scope_tree.read().ok()?;
+            context_start(scope_tree.as_ref()?, uri, text.char_to_byte(end))
+        };
+        let start = text.byte_to_char(start.min(text.len_bytes())).min(end);
+        let mut comps = self.srcs.get_completions(token, byte_idx, uri)?;
+        if let Some(path) = expected_type_path(text, pos, token, start) {
+            let literals: Vec<CompletionItem> = self
+                .srcs
+                .get_enum_completions(&path, byte_idx, uri)?
+                .into_iter()
+                .filter(|x| {
+                    x.filter_text
+                        .as_ref()
+                        .unwrap_or(&x.label)
+                        .starts_with(token)
+                })
+                .collect();
+            comps.items.retain(|x| {
+                !literals
+                    .iter()
+                    .any(|y| y.filter_text.as_ref().unwrap_or(&y.label) == &x.label)
+            });
+            for (i, mut literal) in literals.into_iter().enumerate() {
+                literal.sort_text = Some(format!("0{}", literal.label));
+                literal.preselect = Some(i == 0);
+                comps.items.insert(i, literal);
+            }
+        }
+        // modules and interfaces expand to an instantiation where a module item is expected
+        let line = text.line(pos.line as usize);
+        let cursor = line.utf16_cu_to_char(pos.character as usize);
+        let statement_start = line
+            .chars()
+            .take(cursor.saturating_sub(token.chars().count()))
+            .all(|x| x.is_whitespace());
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let scope_tree = scope_tree.as_ref()?;
+        if statement_start && at_module_item(scope_tree, uri, byte_idx) {
+            for item in comps.items.iter_mut() {
+                if let Some(module) = scope_tree
+                    .scopes()
+                    .iter()
+                    .find(|x| x.ident() == item.label && is_design_unit(x.as_ref()))
+                {
+                    let ranked = instantiation_completion(module.as_ref());
+                    *item = CompletionItem {
+                        sort_text: item.sort_text.take(),
+                        filter_text: item.filter_text.take(),
+                        ..ranked
+                    };
+                }
+            }
+        }
+        // complete the keywords which are legal at the cursor, ranked below all the definitions
+        let code = text.slice(start..end).to_string();
+        let (context, closers) = syntax_context(&code);
+        debug!("keyword context: {:?}", context);
+        comps.items.extend::<Vec<CompletionItem>>(
+            self.key_comps
+                .iter()
+                .filter(|x| {
+                    fuzzy_match(&x.label, token) && keyword_allowed(&x.label, context, closers)
+                })
+                .map(|x| {
+                    let mut item = x.clone();
+                    rank_completion(&mut item, 4, token);
+                    item
+                })
+                .collect(),
+        );
+        // large projects can have too many matches to send, the client asks again as the user
+        // types if the list is incomplete
+        if comps.items.len() > MAX_COMPLETIONS {
+            comps
+                .items
+                .sort_by(|x, y| x.sort_text.cmp(&y.sort_text).then(x.label.cmp(&y.label)));
+            comps.items.truncate(MAX_COMPLETIONS);
+            comps.is_incomplete = true;
+        }
+        Some(comps)
+    }
+}
+
+/// where to start scanning for the syntactic context at a location: the start of the design
+/// unit, package or class enclosing it, or else the end of the last one before it
+fn context_start(scope_tree: &GenericScope, url: &Url, byte_idx: usize) -> usize {
+    let mut start = 0;
+    for scope in scope_tree.scopes().iter().filter(|x| &x.url() == url) {
+        if scope.start() <= byte_idx && byte_idx <= scope.end() {
+            return scope.start();
+        } else if scope.end() < byte_idx {
+            start = start.max(scope.end());
+        }
+    }
+    start
+}
+
+/// the hierarchical name of what the user is assigning or comparing to, ex. state in
+/// `state <= ` or `state == `, or else the expression of the case statement being written
+fn expected_type_path(
+    text: &Rope,
+    pos: Position,
+    token: &str,
+    start: usize,
+) -> Option<Vec<String>> {
+    let line = text.line(pos.line as usize);
+    let cursor = line.utf16_cu_to_char(pos.character as usize);
+    let prefix: String = line
+        .chars()
+        .take(cursor.saturating_sub(token.chars().count()))
+        .collect();
+    let prefix = prefix.trim_end();
+    for op in &["===", "!==", "==", "!=", "<=", "="] {
+        if let Some(lhs) = prefix.strip_suffix(op) {
+            return name_path(trailing_name(lhs.trim_end()));
+        }
+    }
+    if !prefix.trim().is_empty() && !prefix.ends_with(',') {
+        return None;
+    }
+    // look upwards for the enclosing case statement, up to the start of the enclosing scope and
+    // skipping over nested ones which have been closed
+    static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let case_re = RE.get_or_init(|| Regex::new(r"\b(endcase|case|casez|casex)\b").unwrap());
+    let end = text.line_to_char(pos.line as usize);
+    let code = text.slice(start.min(end)..end).to_string();
+    let keywords: Vec<Match> = case_re.find_iter(&code).collect();
+    let mut depth = 0;
+    for keyword in keywords.into_iter().rev() {
+        if keyword.as_str() == "endcase" {
+            depth += 1;
+        } else if depth > 0 {
+            depth -= 1;
+        } else {
+            return name_path(case_expr(&code[keyword.end()..])?);
+        }
+    }
+    None
+}
+
+/// the parenthesized expression at the start of some text, ex. `state` in ` (state)\n  IDLE:`
+fn case_expr(text: &str) -> Option<&str> {
+    let text = text.trim_start().strip_prefix('(')?;
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(text[..i].trim()),
+            ')' => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+/// the hierarchical name at the end of some text, ex. `reqs[i].state` in `x = reqs[i].state`
+pub fn trailing_name(text: &str) -> &str {
+    let mut depth = 0;
+    let mut start = text.len();
+    for (i, c) in text.char_indices().rev() {
+        match c {
+            ']' => depth += 1,
+            '[' if depth > 0 => depth -= 1,
+            _ if depth > 0 => (),
+            _ if c.is_alphanumeric() || c == '_' || c == '.' => (),
+            _ => break,
+        }
+        start = i;
+    }
+    &text[start..]
+}
+
+/// split a hierarchical name into its identifiers, removing any selects
+pub fn name_path(name: &str) -> Option<Vec<String>> {
+    let mut stripped = String::new();
+    let mut depth = 0;
+    for c in name.chars() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ if depth == 0 => stripped.push(c),
+            _ => (),
+        }
+    }
+    let path: Vec<String> = stripped.split('.').map(|x| x.to_owned()).collect();
+    if path
+        .iter()
+        .all(|x| x.starts_with(|c: char| c.is_alphabetic() || c == '_'))
+    {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// the package or class named before a `::`, ex. [pkg, cls] in `pkg::cls::`, where token is
+/// what has been typed after the `::`
+fn scope_path(text: &Rope, pos: Position, token: &str) -> Option<Vec<String>> {
+    let line = text.line(pos.line as usize);
+    let cursor = line.utf16_cu_to_char(pos.character as usize);
+    let prefix: String = line
+        .chars()
+        .take(cursor.saturating_sub(token.chars().count()))
+        .collect();
+    let mut rest = prefix.strip_suffix("::")?;
+    let mut path: Vec<String> = Vec::new();
+    loop {
+        let start = rest
+            .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .map_or(0, |x| x + 1);
+        let ident = &rest[start..];
+        if !ident.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            return None;
+        }
+        path.insert(0, ident.to_owned());
+        rest = match rest[..start].strip_suffix("::") {
+            Some(rest) => rest,
+            None => return Some(path),
+        };
+    }
 }
 
 /// get the previous non-whitespace character
@@ -140,6 +520,22 @@ fn get_completion_token(text: &Rope, line: RopeSlice, pos: Position) -> String {
             || c.unwrap() == '['
             || c.unwrap() == ']')
     {
+        if c.unwrap() == ']' {
+            // skip over selects, ex. the [i + 1] in reqs[i + 1].addr
+            let mut depth = 0;
+            while let Some(x) = c {
+                match x {
+                    ']' => depth += 1,
+                    '[' => depth -= 1,
+                    _ => (),
+                }
+                c = line_iter.prev();
+                if depth == 0 {
+                    break;
+                }
+            }
+            continue;
+        }
         token.push(c.unwrap());
         c = line_iter.prev();
     }
@@ -305,7 +701,7 @@ endmodule
 
         let completion_params = CompletionParams {
             text_document_position: TextDocumentPositionParams {
-                text_document: TextDocumentIdentifier { uri },
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
                 position: Position {
                     line: 4,
                     character: 3,
@@ -323,12 +719,18 @@ endmodule
             label: "abc".to_owned(),
             kind: Some(CompletionItemKind::VARIABLE),
             detail: Some("logic".to_string()),
+            data: completion_data(&uri, text.find("abc;").unwrap()),
+            sort_text: Some("20abc".to_owned()),
+            filter_text: Some("abc".to_owned()),
             ..CompletionItem::default()
         };
         let item2 = CompletionItem {
             label: "abcd".to_owned(),
             kind: Some(CompletionItemKind::VARIABLE),
             detail: Some("logic".to_string()),
+            data: completion_data(&uri, text.find("abcd;").unwrap()),
+            sort_text: Some("20abcd".to_owned()),
+            filter_text: Some("abcd".to_owned()),
             ..CompletionItem::default()
         };
         if let CompletionResponse::List(item) = response {
@@ -423,7 +825,7 @@ endmodule
 
         let completion_params = CompletionParams {
             text_document_position: TextDocumentPositionParams {
-                text_document: TextDocumentIdentifier { uri },
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
                 position: Position {
                     line: 4,
                     character: 3,
@@ -441,12 +843,18 @@ endmodule
             label: "abc".to_owned(),
             kind: Some(CompletionItemKind::VARIABLE),
             detail: Some("logic".to_string()),
+            data: completion_data(&uri, text.find("abc;").unwrap()),
+            sort_text: Some("10abc".to_owned()),
+            filter_text: Some("abc".to_owned()),
             ..CompletionItem::default()
         };
         let item3 = CompletionItem {
             label: "aouter".to_owned(),
             kind: Some(CompletionItemKind::VARIABLE),
             detail: Some("logic".to_string()),
+            data: completion_data(&uri, text.find("aouter;").unwrap()),
+            sort_text: Some("20aouter".to_owned()),
+            filter_text: Some("aouter".to_owned()),
             ..CompletionItem::default()
         };
         if let CompletionResponse::List(item) = response {
@@ -516,6 +924,7 @@ endmodule
             label: "abcd".to_owned(),
             kind: Some(CompletionItemKind::VARIABLE),
             detail: Some("wire".to_string()),
+            data: completion_data(&uri, text.find("abcd;").unwrap()),
             ..CompletionItem::default()
         };
         if let CompletionResponse::List(item) = response {
@@ -602,6 +1011,7 @@ endmodule
             label: "abcd".to_owned(),
             kind: Some(CompletionItemKind::VARIABLE),
             detail: Some("wire".to_string()),
+            data: completion_data(&uri, text.find("abcd;").unwrap()),
             ..CompletionItem::default()
         };
         if let CompletionResponse::List(item) = response {
@@ -672,6 +1082,491 @@ endinterface
         assert_eq!(labels, vec!["abcd", "clk"]);
     }
 
+    #[test]
+    fn test_dot_completion_generate() {
+        test_init();
+        let text = r#"module test;
+    for (genvar i = 0; i < 4; i++) begin : g_lane
+        logic sig;
+        if (i > 0) begin : g_inner
+        end
+    end
+    assign x = g_lane[0].
+endmodule
+"#;
+
+        let doc = Rope::from_str(text);
+        let url = Url::parse("file:///test.sv").unwrap();
+        let syntax_tree = parse(&doc, &url, &None, &Vec::new()).unwrap();
+        let scope_tree = get_scopes(&syntax_tree, &url).unwrap();
+        let pos = Position::new(6, 25);
+        let token = get_completion_token(&doc, doc.line(pos.line as usize), pos);
+        let completions = scope_tree.get_dot_completion(
+            token.trim_end_matches('.'),
+            doc.pos_to_byte(&pos),
+            &url,
+            &scope_tree,
+        );
+        let labels: Vec<String> = completions.iter().map(|x| x.label.clone()).collect();
+        assert_eq!(labels, vec!["i", "sig", "g_inner"]);
+    }
+
+    #[test]
+    fn test_dot_completion_typedef() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let text = r#"package my_pkg;
+    typedef struct packed {
+        logic [3:0] id;
+        logic valid;
+    } inner_t;
+    typedef struct packed {
+        logic [31:0] addr;
+        inner_t inner;
+    } req_t;
+    typedef req_t req_alias_t;
+endpackage
+module test;
+    import my_pkg::*;
+    my_pkg::req_t req;
+    req_alias_t reqs [4];
+    assign a = req.
+    assign b = reqs[i + 1].inner.
+endmodule
+"#;
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+        let fid = server.srcs.get_id(&uri);
+        server.srcs.wait_parse_ready(fid, true);
+
+        let complete = |position: Position| -> Vec<String> {
+            let completion_params = CompletionParams {
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position,
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+                context: Some(CompletionContext {
+                    trigger_kind: CompletionTriggerKind::TRIGGER_CHARACTER,
+                    trigger_character: Some(".".to_string()),
+                }),
+            };
+            match server.completion(completion_params).unwrap() {
+                CompletionResponse::List(list) => list.items.into_iter().map(|x| x.label).collect(),
+                _ => panic!(),
+            }
+        };
+        assert_eq!(complete(Position::new(15, 19)), vec!["addr", "inner"]);
+        assert_eq!(complete(Position::new(16, 33)), vec!["id", "valid"]);
+    }
+
+    #[test]
+    fn test_dot_completion_inherited() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let text = r#"package base_pkg;
+    class component;
+        int id;
+        virtual function void run();
+        endfunction
+    endclass
+endpackage
+class driver extends base_pkg::component;
+    int count;
+    virtual function void run();
+    endfunction
+endclass
+module test;
+    driver drv;
+    initial drv.
+endmodule
+"#;
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+        let fid = server.srcs.get_id(&uri);
+        server.srcs.wait_parse_ready(fid, true);
+        let completion_params = CompletionParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position: Position::new(14, 16),
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+            context: Some(CompletionContext {
+                trigger_kind: CompletionTriggerKind::TRIGGER_CHARACTER,
+                trigger_character: Some(".".to_string()),
+            }),
+        };
+        let labels: Vec<String> = match server.completion(completion_params).unwrap() {
+            CompletionResponse::List(list) => list.items.into_iter().map(|x| x.label).collect(),
+            _ => panic!(),
+        };
+        assert_eq!(labels, vec!["count", "run", "id"]);
+    }
+
+    #[test]
+    fn test_instantiation_completion() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let text = r#"module adder #(
+  parameter int WIDTH = 8,
+  parameter string NAME = "$sum"
+) (
+  input logic clk,
+  input logic [WIDTH-1:0] a,
+  output logic [WIDTH-1:0] y
+);
+endmodule
+interface bus_if;
+endinterface
+module top;
+  function void f();
+    add;
+  endfunction
+  add
+endmodule
+"#;
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+        let fid = server.srcs.get_id(&uri);
+        server.srcs.wait_parse_ready(fid, true);
+        let complete = |position: Position| {
+            let params = CompletionParams {
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position,
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+                context: Some(CompletionContext {
+                    trigger_kind: CompletionTriggerKind::INVOKED,
+                    trigger_character: None,
+                }),
+            };
+            match server.completion(params) {
+                Some(CompletionResponse::List(list)) => {
+                    list.items.into_iter().find(|x| x.label == "adder").unwrap()
+                }
+                _ => panic!(),
+            }
+        };
+        let item = complete(Position::new(15, 5));
+        assert_eq!(item.insert_text_format, Some(InsertTextFormat::SNIPPET));
+        assert_eq!(
+            item.insert_text.unwrap(),
+            r#"adder #(
+  .WIDTH(${1:8}),
+  .NAME(${2:"\$sum"})
+) ${3:u_adder} (
+  .clk(${4:clk}), // input logic
+  .a(${5:a}), // input logic [WIDTH - 1:0]
+  .y(${6:y}) // output logic [WIDTH - 1:0]
+);$0"#
+        );
+        // not a module item
+        let item = complete(Position::new(13, 7));
+        assert_eq!(item.insert_text, None);
+    }
+
+    #[test]
+    fn test_instantiation_member_completion() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///adder.sv").unwrap();
+        let uri2 = Url::parse("file:///top.sv").unwrap();
+        let text = r#"module adder #(
+  parameter int WIDTH = 8,
+  parameter bit SIGNED = 0,
+  localparam int MSB = WIDTH - 1
+) (
+  input logic clk,
+  input logic [WIDTH-1:0] a,
+  output logic [WIDTH-1:0] y
+);
+endmodule
+"#;
+        let text2 = r#"module top;
+  logic clk;
+  adder #(.WIDTH(4), .) u_adder (
+    .clk(clk),
+    .
+  );
+endmodule
+"#;
+        for (uri, text) in &[(&uri, text), (&uri2, text2)] {
+            server.did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: (*uri).clone(),
+                    language_id: "systemverilog".to_owned(),
+                    version: 0,
+                    text: text.to_string(),
+                },
+            });
+            let fid = server.srcs.get_id(uri);
+            server.srcs.wait_parse_ready(fid, true);
+        }
+        let complete = |position: Position| {
+            let params = CompletionParams {
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri2.clone() },
+                    position,
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+                context: Some(CompletionContext {
+                    trigger_kind: CompletionTriggerKind::TRIGGER_CHARACTER,
+                    trigger_character: Some(".".to_owned()),
+                }),
+            };
+            match server.completion(params) {
+                Some(CompletionResponse::List(list)) => list
+                    .items
+                    .into_iter()
+                    .map(|x| (x.label, x.detail.unwrap_or_default()))
+                    .collect::<Vec<(String, String)>>(),
+                _ => panic!(),
+            }
+        };
+        let pair = |label: &str, detail: &str| (label.to_owned(), detail.to_owned());
+        assert_eq!(
+            complete(Position::new(2, 22)),
+            vec![pair("SIGNED", "parameter bit")]
+        );
+        assert_eq!(
+            complete(Position::new(4, 5)),
+            vec![
+                pair("a", "input logic [WIDTH - 1:0]"),
+                pair("y", "output logic [WIDTH - 1:0]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scoped_completion() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///pkg.sv").unwrap();
+        let uri2 = Url::parse("file:///top.sv").unwrap();
+        let text = r#"package pkg;
+  import other_pkg::*;
+  parameter int WIDTH = 8;
+  typedef enum {IDLE, BUSY} state_t;
+  class base;
+    static int count;
+  endclass
+  class packet extends base;
+    static int created;
+    int id;
+    typedef logic [WIDTH-1:0] data_t;
+    static function packet create();
+      return null;
+    endfunction
+    function int size();
+      return 0;
+    endfunction
+  endclass
+  function int add(int a, int b);
+    return a + b;
+  endfunction
+endpackage
+"#;
+        let text2 = r#"module top;
+  initial begin
+    x = pkg::
+    y = pkg::packet::
+    z = pkg::packet::cr
+  end
+endmodule
+"#;
+        for (uri, text) in &[(&uri, text), (&uri2, text2)] {
+            server.did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: (*uri).clone(),
+                    language_id: "systemverilog".to_owned(),
+                    version: 0,
+                    text: text.to_string(),
+                },
+            });
+            let fid = server.srcs.get_id(uri);
+            server.srcs.wait_parse_ready(fid, true);
+        }
+        let complete = |position: Position| {
+            let params = CompletionParams {
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri2.clone() },
+                    position,
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+                context: Some(CompletionContext {
+                    trigger_kind: CompletionTriggerKind::TRIGGER_CHARACTER,
+                    trigger_character: Some(":".to_owned()),
+                }),
+            };
+            match server.completion(params) {
+                Some(CompletionResponse::List(list)) => {
+                    let mut labels: Vec<String> = list.items.into_iter().map(|x| x.label).collect();
+                    labels.sort();
+                    labels
+                }
+                _ => panic!(),
+            }
+        };
+        assert_eq!(
+            complete(Position::new(2, 13)),
+            vec!["BUSY", "IDLE", "WIDTH", "add", "base", "packet", "state_t"]
+        );
+        assert_eq!(
+            complete(Position::new(3, 21)),
+            vec!["count", "create", "created", "data_t"]
+        );
+        assert_eq!(complete(Position::new(4, 23)), vec!["create", "created"]);
+    }
+
+    #[test]
+    fn test_completion_resolve() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let text = r#"module top;
+  // the number of lanes
+  localparam int LANES = 2 * 2;
+  function int add(int a, int b);
+    return a + b;
+  endfunction
+  initial begin
+    int sum = add(1, 2);
+    sum = 
+  end
+endmodule
+"#;
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+        let fid = server.srcs.get_id(&uri);
+        server.srcs.wait_parse_ready(fid, true);
+        let items = match server.completion(CompletionParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: Position::new(8, 10),
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+            context: Some(CompletionContext {
+                trigger_kind: CompletionTriggerKind::INVOKED,
+                trigger_character: None,
+            }),
+        }) {
+            Some(CompletionResponse::List(list)) => list.items,
+            _ => panic!(),
+        };
+        let resolve = |label: &str| {
+            let item = items.iter().find(|x| x.label == label).unwrap().clone();
+            assert_eq!(item.documentation, None);
+            match server.completion_resolve(item).documentation {
+                Some(Documentation::MarkupContent(content)) => content.value,
+                _ => panic!(),
+            }
+        };
+        assert_eq!(
+            resolve("LANES"),
+            "```systemverilog\n// the number of lanes\nlocalparam int LANES = 2 * 2; // = 4 (0x4)\n```\n---\ndefined in `top` in `test.sv`"
+        );
+        assert_eq!(
+            resolve("sum"),
+            "```systemverilog\nint sum = add(1, 2);\n```\n---\ndefined in `top` in `test.sv`"
+        );
+        // keywords have no definition
+        let keyword = items.iter().find(|x| x.label == "null").unwrap().clone();
+        assert_eq!(server.completion_resolve(keyword).documentation, None);
+    }
+
+    #[test]
+    fn test_enum_completion() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let text = r#"package my_pkg;
+    typedef enum logic [1:0] {IDLE, BUSY, DONE} state_t;
+endpackage
+module test;
+    my_pkg::state_t state;
+    logic busy_flag;
+    assign a = state == BUSY;
+    always_comb begin
+        case (state)
+            IDLE: case (busy_flag) 1'b1: ; endcase
+            
+            default: ;
+        endcase
+    end
+endmodule
+"#;
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+        let fid = server.srcs.get_id(&uri);
+        server.srcs.wait_parse_ready(fid, true);
+
+        let complete = |position: Position| -> Vec<CompletionItem> {
+            let completion_params = CompletionParams {
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position,
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+                context: Some(CompletionContext {
+                    trigger_kind: CompletionTriggerKind::INVOKED,
+                    trigger_character: None,
+                }),
+            };
+            match server.completion(completion_params).unwrap() {
+                CompletionResponse::List(list) => list.items,
+                _ => panic!(),
+            }
+        };
+        let items = complete(Position::new(6, 25));
+        assert_eq!(items[0].label, "my_pkg::BUSY");
+        assert_eq!(items[0].preselect, Some(true));
+        assert_eq!(items[0].kind, Some(CompletionItemKind::ENUM_MEMBER));
+        let items = complete(Position::new(10, 12));
+        let labels: Vec<&str> = items.iter().take(3).map(|x| x.label.as_str()).collect();
+        assert_eq!(labels, vec!["my_pkg::IDLE", "my_pkg::BUSY", "my_pkg::DONE"]);
+    }
+
     /*
         #[test]
         fn test_package_completion() {
@@ -778,4 +1673,213 @@ endinterface"#;
             panic!();
         }
     }
+
+    #[test]
+    fn test_macro_completion() {
+        test_init();
+        let server = LSPServer::new(None);
+        let mut d = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("test_data/macro_test.sv");
+        let uri = Url::from_file_path(&d).unwrap();
+        let other_uri = Url::parse("file:///other.sv").unwrap();
+        let text = std::fs::read_to_string(&d).unwrap().replace("`REG(z)", "`");
+        let other_text = "`define DELAY #1\n`define PAIR(a) a\n`define fine(x) x\n";
+        for (uri, text) in &[(&other_uri, other_text), (&uri, text.as_str())] {
+            server.did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: (*uri).clone(),
+                    language_id: "systemverilog".to_owned(),
+                    version: 0,
+                    text: text.to_string(),
+                },
+            });
+            let fid = server.srcs.get_id(uri);
+            server.srcs.wait_parse_ready(fid, true);
+        }
+        let response = server.completion(CompletionParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: Position::new(4, 3),
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+            context: Some(CompletionContext {
+                trigger_kind: CompletionTriggerKind::TRIGGER_CHARACTER,
+                trigger_character: Some("`".to_owned()),
+            }),
+        });
+        let items = match response {
+            Some(CompletionResponse::List(list)) => list.items,
+            _ => panic!(),
+        };
+        let item = |label: &str| items.iter().find(|x| x.label == label).unwrap();
+        // defined in the file, which takes precedence over the project
+        assert_eq!(item("PAIR").detail, Some("`define PAIR(a, b)".to_owned()));
+        assert_eq!(
+            item("PAIR").insert_text,
+            Some("PAIR(${1:a}, ${2:b})".to_owned())
+        );
+        // defined in an include
+        assert_eq!(
+            item("REG").detail,
+            Some("`define REG(name, width = `WIDTH)".to_owned())
+        );
+        assert_eq!(
+            item("REG").insert_text,
+            Some("REG(${1:name}, ${2:`WIDTH})".to_owned())
+        );
+        assert_eq!(
+            item("REG").documentation,
+            Some(Documentation::MarkupContent(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: "```systemverilog\n// register of the given width\n`define REG(name, width = `WIDTH) \\\n  logic [width-1:0] name;\n```".to_owned(),
+            }))
+        );
+        assert_eq!(item("WIDTH").insert_text, None);
+        // defined elsewhere in the project
+        assert_eq!(item("DELAY").detail, Some("`define DELAY".to_owned()));
+        // named like part of the `define keyword
+        assert_eq!(item("fine").detail, Some("`define fine(x)".to_owned()));
+        // the directives are still offered
+        assert!(items.iter().any(|x| x.label == "include"));
+    }
+
+    #[test]
+    fn test_ranked_completion() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let uri2 = Url::parse("file:///pkg.sv").unwrap();
+        let text = r#"module rd_top;
+    logic rd_ptr_q;
+    logic [3:0] wrEn;
+    function void rd();
+        logic rd_data;
+        if (0);
+    endfunction
+endmodule
+"#;
+        let text2 = format!(
+            "package rd_pkg;\nendpackage\n{}",
+            (0..1100)
+                .map(|i| format!("  logic r_{};\n", i))
+                .collect::<String>()
+        );
+        for (uri, text) in &[(&uri2, text2.as_str()), (&uri, text)] {
+            server.did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: (*uri).clone(),
+                    language_id: "systemverilog".to_owned(),
+                    version: 0,
+                    text: text.to_string(),
+                },
+            });
+            let fid = server.srcs.get_id(uri);
+            server.srcs.wait_parse_ready(fid, true);
+        }
+        let complete = |token: &str| {
+            let response = server.completion(CompletionParams {
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position: Position::new(5, 12 + token.len() as u32),
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+                context: Some(CompletionContext {
+                    trigger_kind: CompletionTriggerKind::INVOKED,
+                    trigger_character: None,
+                }),
+            });
+            match response {
+                Some(CompletionResponse::List(mut list)) => {
+                    list.items.sort_by(|x, y| x.sort_text.cmp(&y.sort_text));
+                    list
+                }
+                _ => panic!(),
+            }
+        };
+        let change = |token: &str| {
+            let text = text.replacen("(0)", &format!("({})", token), 1);
+            server.did_change(DidChangeTextDocumentParams {
+                text_document: VersionedTextDocumentIdentifier {
+                    uri: uri.clone(),
+                    version: 1,
+                },
+                content_changes: vec![TextDocumentContentChangeEvent {
+                    range: None,
+                    range_length: None,
+                    text,
+                }],
+            });
+            server.srcs.wait_parse_ready(server.srcs.get_id(&uri), true);
+        };
+        change("rdp");
+        let list = complete("rdp");
+        let labels: Vec<&str> = list.items.iter().map(|x| x.label.as_str()).collect();
+        assert_eq!(labels, vec!["rd_ptr_q", "rd_pkg"]);
+        change("wr_e");
+        let list = complete("wr_e");
+        let labels: Vec<&str> = list.items.iter().map(|x| x.label.as_str()).collect();
+        assert_eq!(labels, vec!["wrEn"]);
+        // local, then module, then global definitions, then keywords
+        change("rd");
+        let list = complete("rd");
+        let labels: Vec<&str> = list.items.iter().map(|x| x.label.as_str()).collect();
+        assert_eq!(
+            labels[..5],
+            ["rd_data", "rd", "rd_ptr_q", "rd_pkg", "rd_top"]
+        );
+        assert!(!list.is_incomplete);
+        // too many matches
+        change("r");
+        let list = complete("r");
+        assert!(list.is_incomplete);
+        assert_eq!(list.items.len(), MAX_COMPLETIONS);
+        assert_eq!(list.items[0].label, "rd_data");
+    }
+
+    #[test]
+    fn test_keyword_completion() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let text = r#"module kw_top(input logic a, b);
+  logic x;
+  assign x = a & en;
+endmodule
+"#;
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+        server.srcs.wait_parse_ready(server.srcs.get_id(&uri), true);
+        let complete = |position: Position| {
+            let response = server.completion(CompletionParams {
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position,
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+                context: Some(CompletionContext {
+                    trigger_kind: CompletionTriggerKind::INVOKED,
+                    trigger_character: None,
+                }),
+            });
+            match response {
+                Some(CompletionResponse::List(list)) => list.items,
+                _ => panic!(),
+            }
+        };
+        // a module can't be closed within an expression
+        let items = complete(Position::new(2, 19));
+        assert!(!items.iter().any(|x| x.label == "endmodule"));
+        // but can where a module item is expected
+        let items = complete(Position::new(3, 2));
+        assert!(items.iter().any(|x| x.label == "endmodule"));
+    }
 }
@@ -0,0 +1,243 @@
+use crate::definition::const_eval::{module_params, param_expr};
+use crate::definition::def_types::*;
+use tower_lsp::lsp_types::*;
+
+/// whether a location is where a module item is expected, ie. directly within a module,
+/// interface or program rather than within a subroutine or class
+pub fn at_module_item(scope_tree: &GenericScope, url: &Url, byte_idx: usize) -> bool {
+    let mut in_design_unit = false;
+    let mut scopes = scope_tree.scopes();
+    while let Some(scope) = scopes
+        .iter()
+        .find(|x| &x.url() == url && x.start() <= byte_idx && byte_idx <= x.end())
+    {
+        match scope.def_type() {
+            DefinitionType::Subroutine | DefinitionType::Class => return false,
+            _ => in_design_unit |= is_design_unit(scope.as_ref()),
+        }
+        scopes = scope.scopes();
+    }
+    in_design_unit
+}
+
+/// a completion of a module or interface name which expands to a full instantiation, with tab
+/// stops for the parameter overrides, the instance name and the port connections
+pub fn instantiation_completion(module: &dyn Scope) -> CompletionItem {
+    CompletionItem {
+        label: module.ident(),
+        kind: Some(module.completion_kind()),
+        detail: Some(format!(
+            "{} instantiation",
+            clean_type_str(&module.type_str(), &module.ident())
+        )),
+        insert_text: Some(instantiation_snippet(module)),
+        insert_text_format: Some(InsertTextFormat::SNIPPET),
+        data: completion_data(&module.url(), module.byte_idx()),
+        ..CompletionItem::default()
+    }
+}
+
+fn instantiation_snippet(module: &dyn Scope) -> String {
+    let ident = module.ident();
+    let mut tab_stop = 0;
+    let mut placeholder = |text: &str| {
+        tab_stop += 1;
+        format!("${{{}:{}}}", tab_stop, escape(text))
+    };
+    let mut snippet = ident.clone();
+    let params: Vec<String> = module_params(module)
+        .into_iter()
+        .map(|param| {
+            let type_str = param.type_str();
+            let default = param_expr(&type_str).map_or(param.ident(), |x| x.1.to_owned());
+            format!("  .{}({})", param.ident(), placeholder(&default))
+        })
+        .collect();
+    if !params.is_empty() {
+        snippet.push_str(&format!(" #(\n{}\n)", params.join(",\n")));
+    }
+    snippet.push_str(&format!(" {} (", placeholder(&format!("u_{}", ident))));
+    let ports: Vec<&dyn Definition> = module
+        .defs()
+        .iter()
+        .filter(|x| x.def_type() == DefinitionType::Port)
+        .map(|x| x.as_ref())
+        .collect();
+    for (i, port) in ports.iter().enumerate() {
+        let separator = if i + 1 < ports.len() { "," } else { "" };
+        snippet.push_str(&format!(
+            "\n  .{}({}){} // {}",
+            port.ident(),
+            placeholder(&port.ident()),
+            separator,
+            clean_type_str(&port.type_str(), &port.ident())
+        ));
+    }
+    if !ports.is_empty() {
+        snippet.push('\n');
+    }
+    snippet.push_str(");$0");
+    snippet
+}
+
+/// the list of a module instantiation a location is within
+#[derive(Debug, PartialEq, Eq)]
+pub enum InstList {
+    Params,
+    Ports,
+}
+
+/// the instantiated module, the list and the names already connected in that list, if
+/// byte_idx is directly within the parameter overrides or port connections of a module
+/// instantiation, ex. `adder #(.WIDTH(8), .|) u_adder (.a(a), .|)`
+pub fn instantiation_list(text: &str, byte_idx: usize) -> Option<(String, InstList, Vec<String>)> {
+    let bytes = text.as_bytes();
+    let byte_idx = byte_idx.min(bytes.len());
+    // find the unmatched paren opening the list
+    let mut depth = 0;
+    let mut open = None;
+    for idx in (0..byte_idx).rev() {
+        match bytes[idx] {
+            b')' => depth += 1,
+            b'(' if depth == 0 => {
+                open = Some(idx);
+                break;
+            }
+            b'(' => depth -= 1,
+            b';' => return None,
+            _ => (),
+        }
+    }
+    let open = open?;
+    let before = skip_space_back(bytes, open);
+    let (module, list) = if before > 0 && bytes[before - 1] == b'#' {
+        let (_, module) = ident_back(bytes, skip_space_back(bytes, before - 1))?;
+        (module, InstList::Params)
+    } else {
+        // the instance name, with any unpacked dimensions
+        let mut end = before;
+        while end > 0 && bytes[end - 1] == b']' {
+            end = skip_space_back(bytes, matching_back(bytes, end - 1, b'[', b']')?);
+        }
+        let (inst_start, _) = ident_back(bytes, end)?;
+        let mut end = skip_space_back(bytes, inst_start);
+        // the parameter overrides
+        if end > 0 && bytes[end - 1] == b')' {
+            end = skip_space_back(bytes, matching_back(bytes, end - 1, b'(', b')')?);
+            if end == 0 || bytes[end - 1] != b'#' {
+                return None;
+            }
+            end = skip_space_back(bytes, end - 1);
+        }
+        let (_, module) = ident_back(bytes, end)?;
+        (module, InstList::Ports)
+    };
+    Some((module, list, connected(bytes, open, byte_idx)))
+}
+
+/// the end of the identifier starting at start
+fn ident_end(bytes: &[u8], start: usize) -> usize {
+    let mut end = start;
+    while end < bytes.len() && is_ident(bytes[end]) {
+        end += 1;
+    }
+    end
+}
+
+/// the names connected by `.name` in the list opened at open, excluding the one being written
+/// at byte_idx
+fn connected(bytes: &[u8], open: usize, byte_idx: usize) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut depth = 0;
+    let mut idx = open + 1;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' if depth == 0 => break,
+            b')' | b']' | b'}' => depth -= 1,
+            b';' => break,
+            b'.' if depth == 0 => {
+                let end = ident_end(bytes, idx + 1);
+                if !(idx < byte_idx && byte_idx <= end) && end > idx + 1 {
+                    names.push(String::from_utf8_lossy(&bytes[idx + 1..end]).into_owned());
+                }
+                idx = end;
+                continue;
+            }
+            _ => (),
+        }
+        idx += 1;
+    }
+    names
+}
+
+fn is_ident(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'$'
+}
+
+fn skip_space_back(bytes: &[u8], mut end: usize) -> usize {
+    while end > 0 && bytes[end - 1].is_ascii_whitespace() {
+        end -= 1;
+    }
+    end
+}
+
+/// the start of the bracketed text closed at close, ex. the `[` of `[3:0]`
+fn matching_back(bytes: &[u8], close: usize, open_c: u8, close_c: u8) -> Option<usize> {
+    let mut depth = 0;
+    for idx in (0..=close).rev() {
+        if bytes[idx] == close_c {
+            depth += 1;
+        } else if bytes[idx] == open_c {
+            depth -= 1;
+            if depth == 0 {
+                return Some(idx);
+            }
+        }
+    }
+    None
+}
+
+/// the identifier ending at end, with its start
+fn ident_back(bytes: &[u8], end: usize) -> Option<(usize, String)> {
+    let mut start = end;
+    while start > 0 && is_ident(bytes[start - 1]) {
+        start -= 1;
+    }
+    if start == end || bytes[start].is_ascii_digit() {
+        return None;
+    }
+    Some((
+        start,
+        String::from_utf8_lossy(&bytes[start..end]).into_owned(),
+    ))
+}
+
+/// the ports or parameters of a module which haven't been connected yet in an instantiation
+pub fn instantiation_members(
+    module: &dyn Scope,
+    list: InstList,
+    connected: &[String],
+) -> Vec<CompletionItem> {
+    let members: Vec<&dyn Definition> = match list {
+        InstList::Params => module_params(module),
+        InstList::Ports => module
+            .defs()
+            .iter()
+            .filter(|x| x.def_type() == DefinitionType::Port)
+            .map(|x| x.as_ref())
+            .collect(),
+    };
+    members
+        .into_iter()
+        .filter(|x| !connected.contains(&x.ident()))
+        .map(|x| x.completion())
+        .collect()
+}
+
+/// escape the characters with a special meaning in snippet placeholders
+pub fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('$', "\\$")
+        .replace('}', "\\}")
+}
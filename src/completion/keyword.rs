@@ -33,6 +33,72 @@ pub fn other_completions(tasks: &[&str]) -> Vec<CompletionItem> {
         .collect()
 }
 
+/// a system task or function with its arguments, optional arguments are in brackets and
+/// variable arguments end with an ellipsis
+pub struct SysTask {
+    pub name: &'static str,
+    // the return type of a system function, empty for a system task
+    pub ret: &'static str,
+    pub args: &'static [&'static str],
+    // a short description, from IEEE 1800
+    pub doc: &'static str,
+}
+
+impl SysTask {
+    /// the signature, ex. `integer $clog2(n)`
+    pub fn signature(&self) -> String {
+        let ret = match self.ret {
+            "" => String::new(),
+            ret => format!("{} ", ret),
+        };
+        match self.args.is_empty() {
+            true => format!("{}${}", ret, self.name),
+            false => format!("{}${}({})", ret, self.name, self.args.join(", ")),
+        }
+    }
+
+    /// the text to insert after the $, with a tab stop for each required argument
+    fn snippet(&self) -> Option<String> {
+        let required: Vec<&str> = self
+            .args
+            .iter()
+            .filter(|x| !x.starts_with('[') && !x.ends_with("..."))
+            .copied()
+            .collect();
+        if required.is_empty() {
+            return None;
+        }
+        let args: Vec<String> = required
+            .iter()
+            .enumerate()
+            .map(|(i, x)| format!("${{{}:{}}}", i + 1, x))
+            .collect();
+        Some(format!(
+            "{}({})$0",
+            self.name.replace('$', "\\$"),
+            args.join(", ")
+        ))
+    }
+}
+
+pub fn sys_task_completions(tasks: &[SysTask]) -> Vec<CompletionItem> {
+    tasks
+        .iter()
+        .map(|x| {
+            let snippet = x.snippet();
+            CompletionItem {
+                label: x.name.to_string(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some(x.signature()),
+                documentation: Some(Documentation::String(x.doc.to_string())),
+                insert_text_format: snippet.as_ref().map(|_| InsertTextFormat::SNIPPET),
+                insert_text: snippet,
+                ..CompletionItem::default()
+            }
+        })
+        .collect()
+}
+
 pub const KEYWORDS: &[(&str, &str)] = &[
     ("accept_on", ""),
     ("alias", ""),
@@ -284,154 +350,1117 @@ pub const KEYWORDS: &[(&str, &str)] = &[
     ("xor", ""),
 ];
 
-pub const SYS_TASKS: &[&str] = &[
-    "finish",
-    "exit",
-    "fatal",
-    "warning",
-    "stop",
-    "error",
-    "info",
-    "realtime",
-    "time",
-    "asserton",
-    "assertkill",
-    "assertpasson",
-    "assertfailon",
-    "assertnonvacuouson",
-    "stime",
-    "printtimescale",
-    "timeformat",
-    "bitstoreal",
-    "bitstoshortreal",
-    "itor",
-    "signed",
-    "cast",
-    "realtobits",
-    "shortrealtobits",
-    "rtoi",
-    "unsigned",
-    "sampled",
-    "fell",
-    "changed",
-    "past_gclk",
-    "fell_gclk",
-    "changed_gclk",
-    "rising_gclk",
-    "steady_gclk",
-    "bits",
-    "typename",
-    "isunbounded",
-    "coverage_control",
-    "coverage_get",
-    "coverage_save",
-    "set_coverage_db_name",
-    "dimensions",
-    "right",
-    "high",
-    "size",
-    "random",
-    "dist_erlang",
-    "dist_normal",
-    "dist_t",
-    "asin",
-    "acos",
-    "atan",
-    "atan2",
-    "hypot",
-    "sinh",
-    "cosh",
-    "tanh",
-    "asinh",
-    "acosh",
-    "atanh",
-    "q_initialize",
-    "q_remove",
-    "q_exam",
-    "q_add",
-    "q_full",
-    "async$and$array",
-    "async$nand$array",
-    "async$or$array",
-    "async$nor$array",
-    "sync$and$array",
-    "sync$nand$array",
-    "sync$or$array",
-    "sync$nor$array",
-    "countones",
-    "onehot0",
-    "fatal",
-    "warning",
-    "dist_chi_square",
-    "dist_exponential",
-    "dist_poisson",
-    "dist_uniform",
-    "countbits",
-    "onehot",
-    "isunknown",
-    "coverage_get_max",
-    "coverage_merge",
-    "get_coverage",
-    "load_coverage_db",
-    "clog2",
-    "ln",
-    "log10",
-    "exp",
-    "sqrt",
-    "pow",
-    "floor",
-    "ceil",
-    "sin",
-    "cos",
-    "tan",
-    "rose",
-    "stable",
-    "past",
-    "rose_gclk",
-    "stable_gclk",
-    "future_gclk",
-    "falling_gclk",
-    "changing_gclk",
-    "unpacked_dimensions",
-    "left",
-    "low",
-    "increment",
-    "assertoff",
-    "assertcontrol",
-    "assertpassoff",
-    "assertfailoff",
-    "assertvacuousoff",
-    "error",
-    "info",
-    "async$and$plane",
-    "async$nand$plane",
-    "async$or$plane",
-    "async$nor$plane",
-    "sync$and$plane",
-    "sync$nand$plane",
-    "sync$or$plane",
-    "sync$nor$plane",
-    "system",
-    "countdrivers",
-    "getpattern",
-    "incsave",
-    "input",
-    "key",
-    "list",
-    "log",
-    "nokey",
-    "nolog",
-    "reset",
-    "reset_count",
-    "reset_value",
-    "restart",
-    "save",
-    "scale",
-    "scope",
-    "showscopes",
-    "showvars",
-    "sreadmemb",
-    "sreadmemh",
+pub const SYS_TASKS: &[SysTask] = &[
+    SysTask {
+        name: "finish",
+        ret: "",
+        args: &["[finish_number]"],
+        doc: "Ends the simulation, the finish number selects the diagnostics printed.",
+    },
+    SysTask {
+        name: "stop",
+        ret: "",
+        args: &["[finish_number]"],
+        doc: "Suspends the simulation.",
+    },
+    SysTask {
+        name: "exit",
+        ret: "",
+        args: &[],
+        doc: "Waits for all programs to complete, then ends the simulation.",
+    },
+    SysTask {
+        name: "display",
+        ret: "",
+        args: &["[format]", "args..."],
+        doc: "Prints the formatted arguments, followed by a newline.",
+    },
+    SysTask {
+        name: "displayb",
+        ret: "",
+        args: &["args..."],
+        doc: "Prints the arguments in binary by default, followed by a newline.",
+    },
+    SysTask {
+        name: "displayh",
+        ret: "",
+        args: &["args..."],
+        doc: "Prints the arguments in hexadecimal by default, followed by a newline.",
+    },
+    SysTask {
+        name: "displayo",
+        ret: "",
+        args: &["args..."],
+        doc: "Prints the arguments in octal by default, followed by a newline.",
+    },
+    SysTask {
+        name: "write",
+        ret: "",
+        args: &["[format]", "args..."],
+        doc: "Prints the formatted arguments, without a newline.",
+    },
+    SysTask {
+        name: "writeb",
+        ret: "",
+        args: &["args..."],
+        doc: "Prints the arguments in binary by default, without a newline.",
+    },
+    SysTask {
+        name: "writeh",
+        ret: "",
+        args: &["args..."],
+        doc: "Prints the arguments in hexadecimal by default, without a newline.",
+    },
+    SysTask {
+        name: "writeo",
+        ret: "",
+        args: &["args..."],
+        doc: "Prints the arguments in octal by default, without a newline.",
+    },
+    SysTask {
+        name: "strobe",
+        ret: "",
+        args: &["[format]", "args..."],
+        doc: "Prints the formatted arguments at the end of the current time step.",
+    },
+    SysTask {
+        name: "monitor",
+        ret: "",
+        args: &["[format]", "args..."],
+        doc: "Prints the formatted arguments whenever one of them changes.",
+    },
+    SysTask {
+        name: "monitoron",
+        ret: "",
+        args: &[],
+        doc: "Enables the monitor.",
+    },
+    SysTask {
+        name: "monitoroff",
+        ret: "",
+        args: &[],
+        doc: "Disables the monitor.",
+    },
+    SysTask {
+        name: "fatal",
+        ret: "",
+        args: &["[finish_number]", "[format]", "args..."],
+        doc: "Reports a fatal error and ends the simulation.",
+    },
+    SysTask {
+        name: "error",
+        ret: "",
+        args: &["[format]", "args..."],
+        doc: "Reports a run-time error.",
+    },
+    SysTask {
+        name: "warning",
+        ret: "",
+        args: &["[format]", "args..."],
+        doc: "Reports a run-time warning.",
+    },
+    SysTask {
+        name: "info",
+        ret: "",
+        args: &["[format]", "args..."],
+        doc: "Reports an informative message.",
+    },
+    SysTask {
+        name: "fopen",
+        ret: "int",
+        args: &["filename", "[mode]"],
+        doc: "Opens a file, returning a file descriptor, or 0 if the file can't be opened.",
+    },
+    SysTask {
+        name: "fclose",
+        ret: "",
+        args: &["fd"],
+        doc: "Closes a file.",
+    },
+    SysTask {
+        name: "fdisplay",
+        ret: "",
+        args: &["fd", "[format]", "args..."],
+        doc: "Writes the formatted arguments to a file, followed by a newline.",
+    },
+    SysTask {
+        name: "fwrite",
+        ret: "",
+        args: &["fd", "[format]", "args..."],
+        doc: "Writes the formatted arguments to a file, without a newline.",
+    },
+    SysTask {
+        name: "fstrobe",
+        ret: "",
+        args: &["fd", "[format]", "args..."],
+        doc: "Writes the formatted arguments to a file at the end of the current time step.",
+    },
+    SysTask {
+        name: "fmonitor",
+        ret: "",
+        args: &["fd", "[format]", "args..."],
+        doc: "Writes the formatted arguments to a file whenever one of them changes.",
+    },
+    SysTask {
+        name: "fgetc",
+        ret: "int",
+        args: &["fd"],
+        doc: "Reads a character from a file, returning EOF on an error.",
+    },
+    SysTask {
+        name: "ungetc",
+        ret: "int",
+        args: &["c", "fd"],
+        doc: "Pushes a character back to a file.",
+    },
+    SysTask {
+        name: "fgets",
+        ret: "int",
+        args: &["str", "fd"],
+        doc: "Reads a line from a file into str, returning the number of characters read.",
+    },
+    SysTask {
+        name: "fscanf",
+        ret: "int",
+        args: &["fd", "format", "args..."],
+        doc: "Reads formatted data from a file, returning the number of items read.",
+    },
+    SysTask {
+        name: "sscanf",
+        ret: "int",
+        args: &["str", "format", "args..."],
+        doc: "Reads formatted data from a string, returning the number of items read.",
+    },
+    SysTask {
+        name: "fread",
+        ret: "int",
+        args: &["variable", "fd", "[start]", "[count]"],
+        doc: "Reads binary data from a file into a variable or memory.",
+    },
+    SysTask {
+        name: "ftell",
+        ret: "int",
+        args: &["fd"],
+        doc: "Returns the offset of the read position in a file.",
+    },
+    SysTask {
+        name: "fseek",
+        ret: "int",
+        args: &["fd", "offset", "operation"],
+        doc: "Sets the read position in a file, returning 0 on success.",
+    },
+    SysTask {
+        name: "rewind",
+        ret: "int",
+        args: &["fd"],
+        doc: "Resets the read position to the start of a file.",
+    },
+    SysTask {
+        name: "fflush",
+        ret: "",
+        args: &["[fd]"],
+        doc: "Writes any buffered output to a file, or to all open files.",
+    },
+    SysTask {
+        name: "ferror",
+        ret: "int",
+        args: &["fd", "str"],
+        doc: "Returns the error code of the most recent file operation, with its description in str.",
+    },
+    SysTask {
+        name: "feof",
+        ret: "int",
+        args: &["fd"],
+        doc: "Returns a non-zero value if the end of a file has been reached.",
+    },
+    SysTask {
+        name: "readmemb",
+        ret: "",
+        args: &["filename", "memory", "[start_addr]", "[finish_addr]"],
+        doc: "Loads a memory from a file of binary values.",
+    },
+    SysTask {
+        name: "readmemh",
+        ret: "",
+        args: &["filename", "memory", "[start_addr]", "[finish_addr]"],
+        doc: "Loads a memory from a file of hexadecimal values.",
+    },
+    SysTask {
+        name: "writememb",
+        ret: "",
+        args: &["filename", "memory", "[start_addr]", "[finish_addr]"],
+        doc: "Writes a memory to a file as binary values.",
+    },
+    SysTask {
+        name: "writememh",
+        ret: "",
+        args: &["filename", "memory", "[start_addr]", "[finish_addr]"],
+        doc: "Writes a memory to a file as hexadecimal values.",
+    },
+    SysTask {
+        name: "sreadmemb",
+        ret: "",
+        args: &["memory", "start_addr", "finish_addr", "strings..."],
+        doc: "Loads a memory from strings of binary values.",
+    },
+    SysTask {
+        name: "sreadmemh",
+        ret: "",
+        args: &["memory", "start_addr", "finish_addr", "strings..."],
+        doc: "Loads a memory from strings of hexadecimal values.",
+    },
+    SysTask {
+        name: "sformat",
+        ret: "",
+        args: &["str", "format", "args..."],
+        doc: "Writes the formatted arguments to a string variable.",
+    },
+    SysTask {
+        name: "sformatf",
+        ret: "string",
+        args: &["format", "args..."],
+        doc: "Returns the formatted arguments as a string.",
+    },
+    SysTask {
+        name: "swrite",
+        ret: "",
+        args: &["str", "args..."],
+        doc: "Writes the arguments to a string variable.",
+    },
+    SysTask {
+        name: "psprintf",
+        ret: "string",
+        args: &["format", "args..."],
+        doc: "Returns the formatted arguments as a string, a common alias of $sformatf.",
+    },
+    SysTask {
+        name: "test$plusargs",
+        ret: "int",
+        args: &["string"],
+        doc: "Returns a non-zero value if a plusarg starting with the string was given.",
+    },
+    SysTask {
+        name: "value$plusargs",
+        ret: "int",
+        args: &["user_string", "variable"],
+        doc: "Reads the value of a plusarg into a variable using a format such as \"name=%d\".",
+    },
+    SysTask {
+        name: "time",
+        ret: "time",
+        args: &[],
+        doc: "Returns the current simulation time as a 64-bit integer, scaled to the time unit of the caller.",
+    },
+    SysTask {
+        name: "stime",
+        ret: "int unsigned",
+        args: &[],
+        doc: "Returns the current simulation time as a 32-bit integer, scaled to the time unit of the caller.",
+    },
+    SysTask {
+        name: "realtime",
+        ret: "realtime",
+        args: &[],
+        doc: "Returns the current simulation time as a real number, scaled to the time unit of the caller.",
+    },
+    SysTask {
+        name: "printtimescale",
+        ret: "",
+        args: &["[hierarchical_identifier]"],
+        doc: "Prints the time unit and precision of a module.",
+    },
+    SysTask {
+        name: "timeformat",
+        ret: "",
+        args: &["[units_number]", "[precision_number]", "[suffix_string]", "[minimum_field_width]"],
+        doc: "Sets how %t formats times.",
+    },
+    SysTask {
+        name: "bitstoreal",
+        ret: "real",
+        args: &["bit_val"],
+        doc: "Converts a 64-bit pattern to a real.",
+    },
+    SysTask {
+        name: "realtobits",
+        ret: "bit [63:0]",
+        args: &["real_val"],
+        doc: "Converts a real to its 64-bit pattern.",
+    },
+    SysTask {
+        name: "bitstoshortreal",
+        ret: "shortreal",
+        args: &["bit_val"],
+        doc: "Converts a 32-bit pattern to a shortreal.",
+    },
+    SysTask {
+        name: "shortrealtobits",
+        ret: "bit [31:0]",
+        args: &["shortreal_val"],
+        doc: "Converts a shortreal to its 32-bit pattern.",
+    },
+    SysTask {
+        name: "itor",
+        ret: "real",
+        args: &["int_val"],
+        doc: "Converts an integer to a real.",
+    },
+    SysTask {
+        name: "rtoi",
+        ret: "integer",
+        args: &["real_val"],
+        doc: "Converts a real to an integer by truncation.",
+    },
+    SysTask {
+        name: "signed",
+        ret: "",
+        args: &["val"],
+        doc: "Returns the value interpreted as signed.",
+    },
+    SysTask {
+        name: "unsigned",
+        ret: "",
+        args: &["val"],
+        doc: "Returns the value interpreted as unsigned.",
+    },
+    SysTask {
+        name: "cast",
+        ret: "int",
+        args: &["dest_var", "source_exp"],
+        doc: "Assigns the source to the destination if the cast is valid, returning 1 on success.",
+    },
+    SysTask {
+        name: "bits",
+        ret: "int",
+        args: &["expression_or_type"],
+        doc: "Returns the number of bits needed to hold the expression or type.",
+    },
+    SysTask {
+        name: "typename",
+        ret: "string",
+        args: &["expression_or_type"],
+        doc: "Returns the resolved name of the type of the expression or type.",
+    },
+    SysTask {
+        name: "isunbounded",
+        ret: "bit",
+        args: &["constant_expression"],
+        doc: "Returns 1 if the parameter is $.",
+    },
+    SysTask {
+        name: "dimensions",
+        ret: "int",
+        args: &["array_or_type"],
+        doc: "Returns the total number of packed and unpacked dimensions.",
+    },
+    SysTask {
+        name: "unpacked_dimensions",
+        ret: "int",
+        args: &["array_or_type"],
+        doc: "Returns the number of unpacked dimensions.",
+    },
+    SysTask {
+        name: "left",
+        ret: "int",
+        args: &["array_or_type", "[dimension]"],
+        doc: "Returns the left bound of a dimension.",
+    },
+    SysTask {
+        name: "right",
+        ret: "int",
+        args: &["array_or_type", "[dimension]"],
+        doc: "Returns the right bound of a dimension.",
+    },
+    SysTask {
+        name: "low",
+        ret: "int",
+        args: &["array_or_type", "[dimension]"],
+        doc: "Returns the lower of the bounds of a dimension.",
+    },
+    SysTask {
+        name: "high",
+        ret: "int",
+        args: &["array_or_type", "[dimension]"],
+        doc: "Returns the higher of the bounds of a dimension.",
+    },
+    SysTask {
+        name: "increment",
+        ret: "int",
+        args: &["array_or_type", "[dimension]"],
+        doc: "Returns 1 if the left bound of a dimension is greater than or equal to the right bound, otherwise -1.",
+    },
+    SysTask {
+        name: "size",
+        ret: "int",
+        args: &["array_or_type", "[dimension]"],
+        doc: "Returns the number of elements in a dimension.",
+    },
+    SysTask {
+        name: "clog2",
+        ret: "integer",
+        args: &["n"],
+        doc: "Returns the ceiling of the log base 2 of the argument, the number of address bits for n entries.",
+    },
+    SysTask {
+        name: "ln",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the natural logarithm.",
+    },
+    SysTask {
+        name: "log10",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the decimal logarithm.",
+    },
+    SysTask {
+        name: "exp",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns e to the power of x.",
+    },
+    SysTask {
+        name: "sqrt",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the square root.",
+    },
+    SysTask {
+        name: "pow",
+        ret: "real",
+        args: &["x", "y"],
+        doc: "Returns x to the power of y.",
+    },
+    SysTask {
+        name: "floor",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the floor.",
+    },
+    SysTask {
+        name: "ceil",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the ceiling.",
+    },
+    SysTask {
+        name: "sin",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the sine.",
+    },
+    SysTask {
+        name: "cos",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the cosine.",
+    },
+    SysTask {
+        name: "tan",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the tangent.",
+    },
+    SysTask {
+        name: "asin",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the arc-sine.",
+    },
+    SysTask {
+        name: "acos",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the arc-cosine.",
+    },
+    SysTask {
+        name: "atan",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the arc-tangent.",
+    },
+    SysTask {
+        name: "atan2",
+        ret: "real",
+        args: &["y", "x"],
+        doc: "Returns the arc-tangent of y/x.",
+    },
+    SysTask {
+        name: "hypot",
+        ret: "real",
+        args: &["x", "y"],
+        doc: "Returns the distance from the origin to the point (x, y).",
+    },
+    SysTask {
+        name: "sinh",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the hyperbolic sine.",
+    },
+    SysTask {
+        name: "cosh",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the hyperbolic cosine.",
+    },
+    SysTask {
+        name: "tanh",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the hyperbolic tangent.",
+    },
+    SysTask {
+        name: "asinh",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the arc-hyperbolic sine.",
+    },
+    SysTask {
+        name: "acosh",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the arc-hyperbolic cosine.",
+    },
+    SysTask {
+        name: "atanh",
+        ret: "real",
+        args: &["x"],
+        doc: "Returns the arc-hyperbolic tangent.",
+    },
+    SysTask {
+        name: "countbits",
+        ret: "int",
+        args: &["expression", "control_bits..."],
+        doc: "Returns the number of bits of the expression with one of the given values.",
+    },
+    SysTask {
+        name: "countones",
+        ret: "int",
+        args: &["expression"],
+        doc: "Returns the number of bits of the expression which are 1.",
+    },
+    SysTask {
+        name: "onehot",
+        ret: "bit",
+        args: &["expression"],
+        doc: "Returns 1 if exactly one bit of the expression is 1.",
+    },
+    SysTask {
+        name: "onehot0",
+        ret: "bit",
+        args: &["expression"],
+        doc: "Returns 1 if at most one bit of the expression is 1.",
+    },
+    SysTask {
+        name: "isunknown",
+        ret: "bit",
+        args: &["expression"],
+        doc: "Returns 1 if any bit of the expression is X or Z.",
+    },
+    SysTask {
+        name: "random",
+        ret: "int",
+        args: &["[seed]"],
+        doc: "Returns a signed 32-bit random number.",
+    },
+    SysTask {
+        name: "urandom",
+        ret: "int unsigned",
+        args: &["[seed]"],
+        doc: "Returns an unsigned 32-bit random number, which is thread stable.",
+    },
+    SysTask {
+        name: "urandom_range",
+        ret: "int unsigned",
+        args: &["maxval", "[minval]"],
+        doc: "Returns an unsigned random number in the range of minval to maxval, minval defaults to 0.",
+    },
+    SysTask {
+        name: "dist_uniform",
+        ret: "int",
+        args: &["seed", "start", "end"],
+        doc: "Returns a random number with a uniform distribution.",
+    },
+    SysTask {
+        name: "dist_normal",
+        ret: "int",
+        args: &["seed", "mean", "standard_deviation"],
+        doc: "Returns a random number with a normal distribution.",
+    },
+    SysTask {
+        name: "dist_exponential",
+        ret: "int",
+        args: &["seed", "mean"],
+        doc: "Returns a random number with an exponential distribution.",
+    },
+    SysTask {
+        name: "dist_poisson",
+        ret: "int",
+        args: &["seed", "mean"],
+        doc: "Returns a random number with a Poisson distribution.",
+    },
+    SysTask {
+        name: "dist_chi_square",
+        ret: "int",
+        args: &["seed", "degree_of_freedom"],
+        doc: "Returns a random number with a chi-square distribution.",
+    },
+    SysTask {
+        name: "dist_t",
+        ret: "int",
+        args: &["seed", "degree_of_freedom"],
+        doc: "Returns a random number with a Student's T distribution.",
+    },
+    SysTask {
+        name: "dist_erlang",
+        ret: "int",
+        args: &["seed", "k_stage", "mean"],
+        doc: "Returns a random number with an Erlang distribution.",
+    },
+    SysTask {
+        name: "asserton",
+        ret: "",
+        args: &["[levels]", "[list_of_scopes_or_assertions...]"],
+        doc: "Re-enables the execution of assertions.",
+    },
+    SysTask {
+        name: "assertoff",
+        ret: "",
+        args: &["[levels]", "[list_of_scopes_or_assertions...]"],
+        doc: "Stops the checking of assertions, until $asserton.",
+    },
+    SysTask {
+        name: "assertkill",
+        ret: "",
+        args: &["[levels]", "[list_of_scopes_or_assertions...]"],
+        doc: "Aborts the assertions currently executing and stops their checking, until $asserton.",
+    },
+    SysTask {
+        name: "assertcontrol",
+        ret: "",
+        args: &["control_type", "[assertion_type]", "[directive_type]", "[levels]", "[list_of_scopes_or_assertions...]"],
+        doc: "Controls the evaluation of assertions, the control type selects the action.",
+    },
+    SysTask {
+        name: "assertpasson",
+        ret: "",
+        args: &["[levels]", "[list_of_scopes_or_assertions...]"],
+        doc: "Enables the pass actions of assertions.",
+    },
+    SysTask {
+        name: "assertpassoff",
+        ret: "",
+        args: &["[levels]", "[list_of_scopes_or_assertions...]"],
+        doc: "Disables the pass actions of assertions.",
+    },
+    SysTask {
+        name: "assertfailon",
+        ret: "",
+        args: &["[levels]", "[list_of_scopes_or_assertions...]"],
+        doc: "Enables the fail actions of assertions.",
+    },
+    SysTask {
+        name: "assertfailoff",
+        ret: "",
+        args: &["[levels]", "[list_of_scopes_or_assertions...]"],
+        doc: "Disables the fail actions of assertions.",
+    },
+    SysTask {
+        name: "assertnonvacuouson",
+        ret: "",
+        args: &["[levels]", "[list_of_scopes_or_assertions...]"],
+        doc: "Enables the pass actions of assertions on nonvacuous successes.",
+    },
+    SysTask {
+        name: "assertvacuousoff",
+        ret: "",
+        args: &["[levels]", "[list_of_scopes_or_assertions...]"],
+        doc: "Disables the pass actions of assertions on vacuous successes.",
+    },
+    SysTask {
+        name: "sampled",
+        ret: "",
+        args: &["expression"],
+        doc: "Returns the value of the expression sampled in the preponed region.",
+    },
+    SysTask {
+        name: "rose",
+        ret: "bit",
+        args: &["expression", "[clocking_event]"],
+        doc: "Returns 1 if the least significant bit changed to 1.",
+    },
+    SysTask {
+        name: "fell",
+        ret: "bit",
+        args: &["expression", "[clocking_event]"],
+        doc: "Returns 1 if the least significant bit changed to 0.",
+    },
+    SysTask {
+        name: "stable",
+        ret: "bit",
+        args: &["expression", "[clocking_event]"],
+        doc: "Returns 1 if the value didn't change.",
+    },
+    SysTask {
+        name: "changed",
+        ret: "bit",
+        args: &["expression", "[clocking_event]"],
+        doc: "Returns 1 if the value changed.",
+    },
+    SysTask {
+        name: "past",
+        ret: "",
+        args: &["expression", "[number_of_ticks]", "[expression2]", "[clocking_event]"],
+        doc: "Returns the sampled value from a number of clock ticks in the past.",
+    },
+    SysTask {
+        name: "past_gclk",
+        ret: "",
+        args: &["expression"],
+        doc: "Returns the sampled value at the previous tick of the global clock.",
+    },
+    SysTask {
+        name: "rose_gclk",
+        ret: "bit",
+        args: &["expression"],
+        doc: "Returns 1 if the least significant bit changed to 1 at the global clock.",
+    },
+    SysTask {
+        name: "fell_gclk",
+        ret: "bit",
+        args: &["expression"],
+        doc: "Returns 1 if the least significant bit changed to 0 at the global clock.",
+    },
+    SysTask {
+        name: "stable_gclk",
+        ret: "bit",
+        args: &["expression"],
+        doc: "Returns 1 if the value didn't change at the global clock.",
+    },
+    SysTask {
+        name: "changed_gclk",
+        ret: "bit",
+        args: &["expression"],
+        doc: "Returns 1 if the value changed at the global clock.",
+    },
+    SysTask {
+        name: "future_gclk",
+        ret: "",
+        args: &["expression"],
+        doc: "Returns the sampled value at the next tick of the global clock.",
+    },
+    SysTask {
+        name: "rising_gclk",
+        ret: "bit",
+        args: &["expression"],
+        doc: "Returns 1 if the least significant bit will change to 1 at the next tick of the global clock.",
+    },
+    SysTask {
+        name: "falling_gclk",
+        ret: "bit",
+        args: &["expression"],
+        doc: "Returns 1 if the least significant bit will change to 0 at the next tick of the global clock.",
+    },
+    SysTask {
+        name: "steady_gclk",
+        ret: "bit",
+        args: &["expression"],
+        doc: "Returns 1 if the value won't change at the next tick of the global clock.",
+    },
+    SysTask {
+        name: "changing_gclk",
+        ret: "bit",
+        args: &["expression"],
+        doc: "Returns 1 if the value will change at the next tick of the global clock.",
+    },
+    SysTask {
+        name: "coverage_control",
+        ret: "int",
+        args: &["control_constant", "coverage_type", "scope_def", "modules_or_instance"],
+        doc: "Controls the collection of coverage.",
+    },
+    SysTask {
+        name: "coverage_get_max",
+        ret: "int",
+        args: &["coverage_type", "scope_def", "modules_or_instance"],
+        doc: "Returns the number of coverable items.",
+    },
+    SysTask {
+        name: "coverage_get",
+        ret: "int",
+        args: &["coverage_type", "scope_def", "modules_or_instance"],
+        doc: "Returns the number of covered items.",
+    },
+    SysTask {
+        name: "coverage_merge",
+        ret: "int",
+        args: &["coverage_type", "name"],
+        doc: "Loads and merges coverage data from a database.",
+    },
+    SysTask {
+        name: "coverage_save",
+        ret: "int",
+        args: &["coverage_type", "name"],
+        doc: "Saves the current coverage data to a database.",
+    },
+    SysTask {
+        name: "get_coverage",
+        ret: "real",
+        args: &[],
+        doc: "Returns the overall coverage of all coverage groups, as a percentage.",
+    },
+    SysTask {
+        name: "set_coverage_db_name",
+        ret: "",
+        args: &["filename"],
+        doc: "Sets the filename of the coverage database.",
+    },
+    SysTask {
+        name: "load_coverage_db",
+        ret: "",
+        args: &["filename"],
+        doc: "Loads the cumulative coverage from a database.",
+    },
+    SysTask {
+        name: "q_initialize",
+        ret: "",
+        args: &["q_id", "q_type", "max_length", "status"],
+        doc: "Creates a new queue.",
+    },
+    SysTask {
+        name: "q_add",
+        ret: "",
+        args: &["q_id", "job_id", "inform_id", "status"],
+        doc: "Adds an entry to a queue.",
+    },
+    SysTask {
+        name: "q_remove",
+        ret: "",
+        args: &["q_id", "job_id", "inform_id", "status"],
+        doc: "Removes an entry from a queue.",
+    },
+    SysTask {
+        name: "q_full",
+        ret: "int",
+        args: &["q_id", "status"],
+        doc: "Returns 1 if a queue is full.",
+    },
+    SysTask {
+        name: "q_exam",
+        ret: "",
+        args: &["q_id", "q_stat_code", "q_stat_value", "status"],
+        doc: "Provides statistical information about a queue.",
+    },
+    SysTask {
+        name: "async$and$array",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models an asynchronous AND logic array.",
+    },
+    SysTask {
+        name: "async$nand$array",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models an asynchronous NAND logic array.",
+    },
+    SysTask {
+        name: "async$or$array",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models an asynchronous OR logic array.",
+    },
+    SysTask {
+        name: "async$nor$array",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models an asynchronous NOR logic array.",
+    },
+    SysTask {
+        name: "sync$and$array",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models a synchronous AND logic array.",
+    },
+    SysTask {
+        name: "sync$nand$array",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models a synchronous NAND logic array.",
+    },
+    SysTask {
+        name: "sync$or$array",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models a synchronous OR logic array.",
+    },
+    SysTask {
+        name: "sync$nor$array",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models a synchronous NOR logic array.",
+    },
+    SysTask {
+        name: "async$and$plane",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models an asynchronous AND logic plane.",
+    },
+    SysTask {
+        name: "async$nand$plane",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models an asynchronous NAND logic plane.",
+    },
+    SysTask {
+        name: "async$or$plane",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models an asynchronous OR logic plane.",
+    },
+    SysTask {
+        name: "async$nor$plane",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models an asynchronous NOR logic plane.",
+    },
+    SysTask {
+        name: "sync$and$plane",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models a synchronous AND logic plane.",
+    },
+    SysTask {
+        name: "sync$nand$plane",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models a synchronous NAND logic plane.",
+    },
+    SysTask {
+        name: "sync$or$plane",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models a synchronous OR logic plane.",
+    },
+    SysTask {
+        name: "sync$nor$plane",
+        ret: "",
+        args: &["memory", "input_terms", "output_terms"],
+        doc: "Models a synchronous NOR logic plane.",
+    },
+    SysTask {
+        name: "system",
+        ret: "int",
+        args: &["[terminal_command_line]"],
+        doc: "Runs a shell command, returning its exit status.",
+    },
+    SysTask {
+        name: "countdrivers",
+        ret: "int",
+        args: &["net", "[net_is_forced]", "[number_of_01x_drivers]", "[number_of_0_drivers]", "[number_of_1_drivers]", "[number_of_x_drivers]"],
+        doc: "Counts the drivers of a net, returning 1 if it has more than one.",
+    },
+    SysTask {
+        name: "getpattern",
+        ret: "",
+        args: &["mem_element"],
+        doc: "Assigns a memory element to a list of scalar nets.",
+    },
+    SysTask {
+        name: "incsave",
+        ret: "",
+        args: &["filename"],
+        doc: "Saves the changes since the last $save.",
+    },
+    SysTask {
+        name: "input",
+        ret: "",
+        args: &["filename"],
+        doc: "Reads commands from a file.",
+    },
+    SysTask {
+        name: "key",
+        ret: "",
+        args: &["[filename]"],
+        doc: "Saves the interactive commands to a key file.",
+    },
+    SysTask {
+        name: "nokey",
+        ret: "",
+        args: &[],
+        doc: "Disables the key file.",
+    },
+    SysTask {
+        name: "list",
+        ret: "",
+        args: &["[hierarchical_identifier]"],
+        doc: "Lists the source of a scope.",
+    },
+    SysTask {
+        name: "log",
+        ret: "",
+        args: &["[filename]"],
+        doc: "Saves the output to a log file.",
+    },
+    SysTask {
+        name: "nolog",
+        ret: "",
+        args: &[],
+        doc: "Disables the log file.",
+    },
+    SysTask {
+        name: "reset",
+        ret: "",
+        args: &["[stop_value]", "[reset_value]", "[diagnostics_value]"],
+        doc: "Resets the simulation time to 0.",
+    },
+    SysTask {
+        name: "reset_count",
+        ret: "int",
+        args: &[],
+        doc: "Returns the number of times $reset was called.",
+    },
+    SysTask {
+        name: "reset_value",
+        ret: "int",
+        args: &[],
+        doc: "Returns the reset value given to $reset.",
+    },
+    SysTask {
+        name: "restart",
+        ret: "",
+        args: &["filename"],
+        doc: "Restores a simulation saved with $save.",
+    },
+    SysTask {
+        name: "save",
+        ret: "",
+        args: &["filename"],
+        doc: "Saves the simulation state to a file.",
+    },
+    SysTask {
+        name: "scale",
+        ret: "",
+        args: &["hierarchical_identifier"],
+        doc: "Converts a time from the time unit of another module.",
+    },
+    SysTask {
+        name: "scope",
+        ret: "",
+        args: &["hierarchical_identifier"],
+        doc: "Sets the scope for interactive commands.",
+    },
+    SysTask {
+        name: "showscopes",
+        ret: "",
+        args: &["[n]"],
+        doc: "Lists the scopes in the current scope.",
+    },
+    SysTask {
+        name: "showvars",
+        ret: "",
+        args: &["[variables...]"],
+        doc: "Shows the status of variables.",
+    },
 ];
 
 pub const DIRECTIVES: &[&str] = &[
@@ -464,3 +1493,29 @@ pub const DIRECTIVES: &[&str] = &[
     "delay_mode_unit",
     "delay_mode_zero",
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sys_task_completions() {
+        let items = sys_task_completions(SYS_TASKS);
+        let item = |label: &str| items.iter().find(|x| x.label == label).unwrap();
+        assert_eq!(
+            item("urandom_range").insert_text.as_deref(),
+            Some("urandom_range(${1:maxval})$0")
+        );
+        assert_eq!(
+            item("urandom_range").detail.as_deref(),
+            Some("int unsigned $urandom_range(maxval, [minval])")
+        );
+        assert_eq!(
+            item("test$plusargs").insert_text.as_deref(),
+            Some("test\\$plusargs(${1:string})$0")
+        );
+        // no arguments are required
+        assert_eq!(item("finish").insert_text, None);
+        assert_eq!(item("finish").insert_text_format, None);
+    }
+}
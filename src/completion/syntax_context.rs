@@ -0,0 +1,721 @@
+use super::keyword::DIRECTIVES;
+
+/// where the cursor is syntactically, which determines the keywords that are legal there
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxContext {
+    // outside of any design unit, package or class
+    Global,
+    // directly within a module, interface, program or generate block
+    ModuleItem,
+    // within the port or parameter list of a header
+    PortList,
+    // within a subroutine or procedural block
+    Statement,
+    // within an expression
+    Expression,
+    ClassItem,
+    PackageItem,
+    // somewhere keywords aren't filtered, ex. a covergroup or property
+    Unknown,
+}
+
+const DATA_TYPES: &[&str] = &[
+    "bit",
+    "byte",
+    "chandle",
+    "event",
+    "int",
+    "integer",
+    "logic",
+    "longint",
+    "real",
+    "realtime",
+    "reg",
+    "shortint",
+    "shortreal",
+    "signed",
+    "string",
+    "time",
+    "unsigned",
+    "enum",
+    "struct",
+    "union",
+    "packed",
+    "type",
+    "var",
+    "const",
+    "virtual",
+];
+
+const NET_TYPES: &[&str] = &[
+    "wire",
+    "tri",
+    "tri0",
+    "tri1",
+    "triand",
+    "trior",
+    "trireg",
+    "wand",
+    "wor",
+    "uwire",
+    "supply0",
+    "supply1",
+    "interconnect",
+];
+
+const DECLARATIONS: &[&str] = &[
+    "typedef",
+    "parameter",
+    "localparam",
+    "import",
+    "let",
+    "automatic",
+    "static",
+    "function",
+    "task",
+    "nettype",
+];
+
+const GLOBAL: &[&str] = &[
+    "module",
+    "macromodule",
+    "interface",
+    "program",
+    "package",
+    "class",
+    "checker",
+    "config",
+    "primitive",
+    "bind",
+    "export",
+    "extern",
+    "timeunit",
+    "timeprecision",
+    "library",
+    "include",
+];
+
+const MODULE_ITEMS: &[&str] = &[
+    "input",
+    "output",
+    "inout",
+    "ref",
+    "always",
+    "always_comb",
+    "always_ff",
+    "always_latch",
+    "initial",
+    "final",
+    "assign",
+    "alias",
+    "generate",
+    "genvar",
+    "for",
+    "if",
+    "else",
+    "case",
+    "begin",
+    "modport",
+    "clocking",
+    "default",
+    "global",
+    "property",
+    "sequence",
+    "assert",
+    "assume",
+    "cover",
+    "restrict",
+    "covergroup",
+    "bind",
+    "defparam",
+    "specify",
+    "specparam",
+    "class",
+    "interface",
+    "module",
+    "program",
+    "checker",
+    "export",
+    "extern",
+    "pulldown",
+    "pullup",
+    "and",
+    "nand",
+    "or",
+    "nor",
+    "xor",
+    "xnor",
+    "buf",
+    "not",
+    "bufif0",
+    "bufif1",
+    "notif0",
+    "notif1",
+    "nmos",
+    "pmos",
+    "cmos",
+    "rnmos",
+    "rpmos",
+    "rcmos",
+    "tran",
+    "tranif0",
+    "tranif1",
+    "rtran",
+    "rtranif0",
+    "rtranif1",
+    "timeunit",
+    "timeprecision",
+    "scalared",
+    "vectored",
+];
+
+const PORTS: &[&str] = &[
+    "input",
+    "output",
+    "inout",
+    "ref",
+    "interface",
+    "parameter",
+    "localparam",
+];
+
+const STATEMENTS: &[&str] = &[
+    "typedef",
+    "automatic",
+    "static",
+    "begin",
+    "fork",
+    "if",
+    "else",
+    "case",
+    "casex",
+    "casez",
+    "unique",
+    "unique0",
+    "priority",
+    "for",
+    "foreach",
+    "while",
+    "do",
+    "repeat",
+    "forever",
+    "return",
+    "break",
+    "continue",
+    "wait",
+    "wait_order",
+    "disable",
+    "assert",
+    "assume",
+    "cover",
+    "expect",
+    "assign",
+    "deassign",
+    "force",
+    "release",
+    "randcase",
+    "randsequence",
+    "this",
+    "super",
+    "void",
+    "default",
+    "std",
+    "local",
+];
+
+const EXPRESSIONS: &[&str] = &[
+    "inside",
+    "dist",
+    "with",
+    "new",
+    "null",
+    "this",
+    "super",
+    "tagged",
+    "type",
+    "signed",
+    "unsigned",
+    "const",
+    "std",
+    "local",
+    "iff",
+    "matches",
+    "posedge",
+    "negedge",
+    "edge",
+    "default",
+    "bit",
+    "byte",
+    "int",
+    "integer",
+    "logic",
+    "longint",
+    "real",
+    "shortint",
+    "shortreal",
+    "string",
+    "time",
+];
+
+const CLASS_ITEMS: &[&str] = &[
+    "function",
+    "task",
+    "virtual",
+    "pure",
+    "extern",
+    "static",
+    "protected",
+    "local",
+    "rand",
+    "randc",
+    "constraint",
+    "covergroup",
+    "class",
+    "typedef",
+    "parameter",
+    "localparam",
+    "interface",
+];
+
+const PACKAGE_ITEMS: &[&str] = &[
+    "class",
+    "virtual",
+    "covergroup",
+    "import",
+    "export",
+    "extern",
+    "timeunit",
+    "timeprecision",
+    "property",
+    "sequence",
+    "checker",
+    "interface",
+];
+
+/// whether a keyword is legal in a context, given the keywords which close the innermost
+/// construct, ex. `endmodule` or `end`
+pub fn keyword_allowed(keyword: &str, context: SyntaxContext, closers: &[&str]) -> bool {
+    // a construct can only be closed where a statement or item could start
+    let closable = !matches!(context, SyntaxContext::Expression | SyntaxContext::PortList);
+    if closable && closers.contains(&keyword) {
+        return true;
+    }
+    let lists: &[&[&str]] = match context {
+        SyntaxContext::Global => &[GLOBAL, DATA_TYPES, NET_TYPES, DECLARATIONS],
+        SyntaxContext::ModuleItem => &[MODULE_ITEMS, DATA_TYPES, NET_TYPES, DECLARATIONS],
+        SyntaxContext::PortList => &[PORTS, DATA_TYPES, NET_TYPES],
+        SyntaxContext::Statement => &[STATEMENTS, DATA_TYPES],
+        SyntaxContext::Expression => &[EXPRESSIONS],
+        SyntaxContext::ClassItem => &[CLASS_ITEMS, DATA_TYPES],
+        SyntaxContext::PackageItem => &[PACKAGE_ITEMS, DATA_TYPES, NET_TYPES, DECLARATIONS],
+        SyntaxContext::Unknown => return true,
+    };
+    lists.iter().any(|x| x.contains(&keyword))
+}
+
+/// the keywords which close a construct opened by a keyword
+fn closers(opener: &str) -> &'static [&'static str] {
+    match opener {
+        "module" | "macromodule" => &["endmodule"],
+        "interface" => &["endinterface"],
+        "program" => &["endprogram"],
+        "checker" => &["endchecker"],
+        "package" => &["endpackage"],
+        "class" => &["endclass"],
+        "function" => &["endfunction"],
+        "task" => &["endtask"],
+        "covergroup" => &["endgroup"],
+        "property" => &["endproperty"],
+        "sequence" | "randsequence" => &["endsequence"],
+        "primitive" => &["endprimitive"],
+        "config" => &["endconfig"],
+        "begin" => &["end"],
+        "fork" => &["join", "join_any", "join_none"],
+        "case" | "casex" | "casez" | "randcase" => &["endcase"],
+        "generate" => &["endgenerate"],
+        "specify" => &["endspecify"],
+        _ => &[],
+    }
+}
+
+/// a construct the cursor may be in
+#[derive(Debug)]
+enum Frame {
+    // between the keyword opening a construct and the `;` ending its header, the body context
+    // is None if there is no body, ex. an extern method
+    Header(&'static str, Option<SyntaxContext>),
+    Body(&'static str, SyntaxContext),
+    // the statement of an always, initial or final block
+    Procedure,
+}
+
+impl Frame {
+    fn context(&self) -> SyntaxContext {
+        match self {
+            Frame::Header(..) => SyntaxContext::Unknown,
+            Frame::Body(_, context) => *context,
+            Frame::Procedure => SyntaxContext::Statement,
+        }
+    }
+}
+
+/// the keyword a token opens a construct with, and the context of its body
+fn opener(token: &str, context: SyntaxContext) -> Option<(&'static str, SyntaxContext)> {
+    let statement = match context {
+        SyntaxContext::ModuleItem => SyntaxContext::ModuleItem,
+        _ => SyntaxContext::Statement,
+    };
+    let opener = match token {
+        "module" => ("module", SyntaxContext::ModuleItem),
+        "macromodule" => ("macromodule", SyntaxContext::ModuleItem),
+        "interface" => ("interface", SyntaxContext::ModuleItem),
+        "program" => ("program", SyntaxContext::ModuleItem),
+        "checker" => ("checker", SyntaxContext::ModuleItem),
+        "package" => ("package", SyntaxContext::PackageItem),
+        "class" => ("class", SyntaxContext::ClassItem),
+        "function" => ("function", SyntaxContext::Statement),
+        "task" => ("task", SyntaxContext::Statement),
+        "covergroup" => ("covergroup", SyntaxContext::Unknown),
+        "property" => ("property", SyntaxContext::Unknown),
+        "sequence" => ("sequence", SyntaxContext::Unknown),
+        "primitive" => ("primitive", SyntaxContext::Unknown),
+        "config" => ("config", SyntaxContext::Unknown),
+        "randsequence" => ("randsequence", SyntaxContext::Unknown),
+        "specify" => ("specify", SyntaxContext::Unknown),
+        "begin" => ("begin", statement),
+        "fork" => ("fork", SyntaxContext::Statement),
+        "case" => ("case", statement),
+        "casex" => ("casex", statement),
+        "casez" => ("casez", statement),
+        "randcase" => ("randcase", SyntaxContext::Statement),
+        "generate" => ("generate", SyntaxContext::ModuleItem),
+        _ => return None,
+    };
+    Some(opener)
+}
+
+fn has_header(opener: &str) -> bool {
+    matches!(
+        opener,
+        "module"
+            | "macromodule"
+            | "interface"
+            | "program"
+            | "checker"
+            | "package"
+            | "class"
+            | "function"
+            | "task"
+            | "covergroup"
+            | "property"
+            | "sequence"
+            | "primitive"
+            | "config"
+    )
+}
+
+/// the tokens of some code, with comments and whitespace removed and string literals replaced
+/// by an empty string
+fn tokens(code: &str) -> Vec<String> {
+    let chars: Vec<char> = code.chars().collect();
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let mut tokens: Vec<String> = Vec::new();
+    let mut idx = 0;
+    while idx < chars.len() {
+        let c = chars[idx];
+        let start = idx;
+        idx += 1;
+        match c {
+            '/' if chars.get(idx) == Some(&'/') => {
+                while idx < chars.len() && chars[idx] != '\n' {
+                    idx += 1;
+                }
+            }
+            '/' if chars.get(idx) == Some(&'*') => {
+                idx += 1;
+                while idx < chars.len() && !(chars[idx - 1] == '*' && chars[idx] == '/') {
+                    idx += 1;
+                }
+                idx += 1;
+            }
+            '"' => {
+                while idx < chars.len() && chars[idx] != '"' {
+                    idx += if chars[idx] == '\\' { 2 } else { 1 };
+                }
+                idx += 1;
+                tokens.push("\"\"".to_owned());
+            }
+            // escaped identifiers
+            '\\' => {
+                while idx < chars.len() && !chars[idx].is_whitespace() {
+                    idx += 1;
+                }
+                tokens.push(chars[start..idx].iter().collect());
+            }
+            // identifiers, keywords, numbers, system tasks and macros
+            _ if is_ident(c)
+                || c == '`'
+                || (c == '\'' && chars.get(idx).is_some_and(|x| x.is_alphanumeric())) =>
+            {
+                while idx < chars.len() && (is_ident(chars[idx]) || chars[idx] == '\'') {
+                    idx += 1;
+                }
+                let token: String = chars[start..idx].iter().collect();
+                match token.strip_prefix('`') {
+                    // compiler directives, skip the rest of the line and any continued lines
+                    Some(directive)
+                        if DIRECTIVES.contains(&directive) && !directive.starts_with("__") =>
+                    {
+                        while idx < chars.len() && (chars[idx] != '\n' || chars[idx - 1] == '\\') {
+                            idx += 1;
+                        }
+                    }
+                    _ => tokens.push(token),
+                }
+            }
+            _ if c.is_whitespace() => (),
+            _ => tokens.push(c.to_string()),
+        }
+    }
+    tokens
+}
+
+/// the syntactic context at the end of some code, found with a tolerant scan of its tokens,
+/// and the keywords which close the innermost construct
+pub fn syntax_context(code: &str) -> (SyntaxContext, &'static [&'static str]) {
+    let mut frames: Vec<Frame> = Vec::new();
+    // the open parentheses, brackets and braces, with whether an expression was being written
+    // before each
+    let mut groups: Vec<bool> = Vec::new();
+    let mut expr = false;
+    // a declaration without a body, ex. `extern function` or `import "DPI-C" function`
+    let mut bodiless = false;
+    // a procedure's statement has ended, but may continue with an else
+    let mut procedure_done = false;
+    let tokens = tokens(code);
+    for (idx, token) in tokens.iter().enumerate() {
+        let prev = match idx {
+            0 => "",
+            _ => tokens[idx - 1].as_str(),
+        };
+        if procedure_done && token != "else" {
+            if let Some(Frame::Procedure) = frames.last() {
+                frames.pop();
+            }
+        }
+        procedure_done = false;
+        let context = frames.last().map_or(SyntaxContext::Global, |x| x.context());
+        match token.as_str() {
+            "(" | "[" | "{" => {
+                groups.push(expr);
+                expr = false;
+            }
+            ")" | "]" | "}" => expr = groups.pop().unwrap_or_default(),
+            _ if !groups.is_empty() => {
+                if token == "," {
+                    expr = false;
+                } else if token.len() == 1 && "=+-*/%&|^!~?<>".contains(token.as_str()) {
+                    expr = true;
+                }
+            }
+            ";" => {
+                expr = false;
+                match frames.last() {
+                    Some(Frame::Header(_, None)) => {
+                        frames.pop();
+                    }
+                    Some(Frame::Header(opener, Some(context))) => {
+                        let frame = Frame::Body(opener, *context);
+                        frames.pop();
+                        frames.push(frame);
+                    }
+                    Some(Frame::Procedure) => procedure_done = true,
+                    _ => (),
+                }
+                bodiless = false;
+            }
+            "," => expr = false,
+            "=" | "+" | "-" | "*" | "/" | "%" | "&" | "|" | "^" | "!" | "~" | "?" | "<" | ">" => {
+                expr = true
+            }
+            "return" | "inside" | "dist" => expr = true,
+            "extern" | "pure" => bodiless = true,
+            "\"\"" if prev == "import" || prev == "export" => bodiless = true,
+            "always" | "always_comb" | "always_ff" | "always_latch" | "initial" | "final"
+                if context == SyntaxContext::ModuleItem =>
+            {
+                expr = false;
+                frames.push(Frame::Procedure);
+            }
+            _ if token.starts_with("end") || token.starts_with("join") => {
+                let open = frames.iter().rposition(|x| match x {
+                    Frame::Header(opener, _) | Frame::Body(opener, _) => {
+                        closers(opener).contains(&token.as_str())
+                    }
+                    Frame::Procedure => false,
+                });
+                if let Some(open) = open {
+                    expr = false;
+                    frames.truncate(open);
+                    if let Some(Frame::Procedure) = frames.last() {
+                        procedure_done = true;
+                    }
+                }
+            }
+            _ => {
+                // keywords which don't open a construct here
+                let skip = match token.as_str() {
+                    "fork" => prev == "wait" || prev == "disable",
+                    "interface" => prev == "virtual",
+                    "class" => prev == "typedef",
+                    "function" | "task" => prev == "with",
+                    "property" | "sequence" => {
+                        matches!(prev, "assert" | "assume" | "cover" | "restrict" | "expect")
+                    }
+                    _ => false,
+                };
+                let opener = match skip {
+                    true => None,
+                    false => opener(token, context),
+                };
+                if let Some((opener, body)) = opener {
+                    expr = false;
+                    // an interface class
+                    if opener == "class" && prev == "interface" {
+                        frames.pop();
+                    }
+                    if has_header(opener) {
+                        let body = match bodiless {
+                            true => None,
+                            false => Some(body),
+                        };
+                        frames.push(Frame::Header(opener, body));
+                    } else {
+                        frames.push(Frame::Body(opener, body));
+                    }
+                } else if matches!(token.as_str(), "else" | "do") {
+                    expr = false;
+                }
+            }
+        }
+    }
+    if procedure_done {
+        if let Some(Frame::Procedure) = frames.last() {
+            frames.pop();
+        }
+    }
+    let frame = frames.last();
+    let context = match frame {
+        _ if expr => SyntaxContext::Expression,
+        Some(Frame::Header(..)) if !groups.is_empty() => SyntaxContext::PortList,
+        _ if !groups.is_empty() => SyntaxContext::Expression,
+        Some(frame) => frame.context(),
+        None => SyntaxContext::Global,
+    };
+    let closers = match frame {
+        _ if matches!(context, SyntaxContext::Expression | SyntaxContext::PortList) => &[],
+        Some(Frame::Body(opener, _)) => closers(opener),
+        _ => &[],
+    };
+    (context, closers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syntax_context() {
+        let context = |code: &str| syntax_context(code);
+        assert_eq!(
+            context("`timescale 1ns/1ps\n"),
+            (SyntaxContext::Global, &[][..])
+        );
+        let module = "module m #(parameter W = 8) (input logic a);\n";
+        assert_eq!(
+            context("module m #(parameter W = "),
+            (SyntaxContext::Expression, &[][..])
+        );
+        assert_eq!(
+            context("module m #(parameter W = 8) (input logic a, "),
+            (SyntaxContext::PortList, &[][..])
+        );
+        assert_eq!(
+            context(module),
+            (SyntaxContext::ModuleItem, &["endmodule"][..])
+        );
+        assert_eq!(
+            context(&format!("{}  assign x = a & ", module)),
+            (SyntaxContext::Expression, &[][..])
+        );
+        assert_eq!(
+            context(&format!("{}  always_ff @(posedge clk)\n", module)),
+            (SyntaxContext::Statement, &[][..])
+        );
+        assert_eq!(
+            context(&format!(
+                "{}  always_ff @(posedge clk) if (a) x <= 1; else x <= 0;\n",
+                module
+            )),
+            (SyntaxContext::ModuleItem, &["endmodule"][..])
+        );
+        assert_eq!(
+            context(&format!("{}  initial begin\n    if (a) ", module)),
+            (SyntaxContext::Statement, &["end"][..])
+        );
+        assert_eq!(
+            context(&format!("{}  initial begin\n    x = f(a, ", module)),
+            (SyntaxContext::Expression, &[][..])
+        );
+        assert_eq!(
+            context(&format!("{}  function int f(int a);\n    ", module)),
+            (SyntaxContext::Statement, &["endfunction"][..])
+        );
+        assert_eq!(
+            context(&format!(
+                "{}  function int f(int a);\n    return a;\n  endfunction\n",
+                module
+            )),
+            (SyntaxContext::ModuleItem, &["endmodule"][..])
+        );
+        let class = "package p;\n  class c extends b;\n    extern function void f();\n";
+        assert_eq!(
+            context(class),
+            (SyntaxContext::ClassItem, &["endclass"][..])
+        );
+        assert_eq!(
+            context(&format!(
+                "{}    // endclass\n    string s = \"endclass\";\n",
+                class
+            )),
+            (SyntaxContext::ClassItem, &["endclass"][..])
+        );
+        assert_eq!(
+            context(&format!("{}  endclass\n", class)),
+            (SyntaxContext::PackageItem, &["endpackage"][..])
+        );
+        assert!(keyword_allowed(
+            "endmodule",
+            SyntaxContext::ModuleItem,
+            &["endmodule"]
+        ));
+        assert!(!keyword_allowed(
+            "endmodule",
+            SyntaxContext::Expression,
+            &["end"]
+        ));
+        assert!(!keyword_allowed(
+            "endmodule",
+            SyntaxContext::Expression,
+            &["endmodule"]
+        ));
+        assert!(!keyword_allowed("end", SyntaxContext::PortList, &["end"]));
+        assert!(!keyword_allowed(
+            "always_ff",
+            SyntaxContext::Statement,
+            &["endfunction"]
+        ));
+        assert!(keyword_allowed("always_ff", SyntaxContext::ModuleItem, &[]));
+        assert!(keyword_allowed("inside", SyntaxContext::Expression, &[]));
+    }
+}
@@ -0,0 +1,91 @@
+use super::instantiation::escape;
+use tower_lsp::lsp_types::*;
+
+/// a completion of a `define given the text of its definition. A macro with formal arguments
+/// expands to a call with tab stops for each argument, holding its name or default value
+pub fn macro_completion(ident: &str, kind: CompletionItemKind, definition: &str) -> CompletionItem {
+    let formals = formal_args(definition, ident);
+    let (detail, snippet) = match &formals {
+        Some(formals) => {
+            let args: Vec<String> = formals
+                .iter()
+                .map(|(name, default)| match default {
+                    Some(default) => format!("{} = {}", name, default),
+                    None => name.clone(),
+                })
+                .collect();
+            let placeholders: Vec<String> = formals
+                .iter()
+                .enumerate()
+                .map(|(i, (name, default))| {
+                    format!(
+                        "${{{}:{}}}",
+                        i + 1,
+                        escape(default.as_ref().unwrap_or(name))
+                    )
+                })
+                .collect();
+            (
+                format!("`define {}({})", ident, args.join(", ")),
+                Some(format!("{}({})", ident, placeholders.join(", "))),
+            )
+        }
+        None => (format!("`define {}", ident), None),
+    };
+    CompletionItem {
+        label: ident.to_owned(),
+        kind: Some(kind),
+        detail: Some(detail),
+        documentation: Some(Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!("```systemverilog\n{}\n```", definition),
+        })),
+        insert_text_format: snippet.as_ref().map(|_| InsertTextFormat::SNIPPET),
+        insert_text: snippet,
+        ..CompletionItem::default()
+    }
+}
+
+/// the formal arguments of a `define and their default values, None if the macro takes no
+/// arguments. The list must immediately follow the macro name
+fn formal_args(definition: &str, ident: &str) -> Option<Vec<(String, Option<String>)>> {
+    let define = definition.find("`define")? + "`define".len();
+    let rest = definition[define..]
+        .trim_start()
+        .strip_prefix(ident)?
+        .strip_prefix('(')?;
+    let mut formals: Vec<(String, Option<String>)> = Vec::new();
+    let mut depth = 0;
+    let mut in_str = false;
+    let mut arg = String::new();
+    let mut push = |arg: &str| {
+        let arg = arg.replace("\\\n", " ");
+        let (name, default) = match arg.split_once('=') {
+            Some((name, default)) => (name, Some(default.trim().to_owned())),
+            None => (arg.as_str(), None),
+        };
+        if !name.trim().is_empty() {
+            formals.push((name.trim().to_owned(), default));
+        }
+    };
+    for c in rest.chars() {
+        match c {
+            '"' => in_str = !in_str,
+            _ if in_str => (),
+            '(' | '[' | '{' => depth += 1,
+            ')' if depth == 0 => {
+                push(&arg);
+                return Some(formals);
+            }
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                push(&arg);
+                arg.clear();
+                continue;
+            }
+            _ => (),
+        }
+        arg.push(c);
+    }
+    None
+}
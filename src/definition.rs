@@ -3,13 +3,24 @@ use crate::server::LSPServer;
 use crate::sources::LSPSupport;
 use log::{debug, trace};
 use ropey::{Rope, RopeSlice};
+use std::sync::atomic::Ordering;
 use sv_parser::*;
+use tower_lsp::lsp_types::request::{
+    GotoDeclarationParams, GotoDeclarationResponse, GotoImplementationParams,
+    GotoImplementationResponse, GotoTypeDefinitionParams, GotoTypeDefinitionResponse,
+};
 use tower_lsp::lsp_types::*;
 
+pub mod const_eval;
+use const_eval::value_hover;
 pub mod def_types;
 pub use def_types::*;
+mod macros;
+use macros::expand_macro;
+pub use macros::macro_definition;
 
 mod extract_defs;
+pub use extract_defs::text_macro_defs;
 use extract_defs::*;
 
 impl LSPServer {
@@ -19,20 +30,123 @@ impl LSPServer {
         let file_id = self.srcs.get_id(&doc).to_owned();
         self.srcs.wait_parse_ready(file_id, false);
         let file = self.srcs.get_file(file_id)?;
-        let file = file.read().ok()?;
-        let token = get_definition_token(file.text.line(pos.line as usize), pos);
-        debug!("goto definition, token: {}", &token);
+        let text = file.read().ok()?.text.clone();
+        if let Some(location) = self.goto_include(&text, &doc, pos) {
+            return Some(GotoDefinitionResponse::Scalar(location));
+        }
+        let line = text.line(pos.line as usize);
+        let path = get_definition_path(line, pos);
+        debug!("goto definition, path: {:?}", &path);
         let scope_tree = self.srcs.scope_tree.read().ok()?;
         trace!("{:#?}", scope_tree.as_ref()?);
-        let def = scope_tree
-            .as_ref()?
-            .get_definition(&token, file.text.pos_to_byte(&pos), &doc)?;
-        let def_pos = file.text.byte_to_pos(def.byte_idx());
-        debug!("def: {:?}", def_pos);
-        Some(GotoDefinitionResponse::Scalar(Location::new(
-            def.url(),
-            Range::new(def_pos, def_pos),
-        )))
+        let scope_tree = scope_tree.as_ref()?;
+        let def = scope_tree.find_path(&path, text.pos_to_byte(&pos), &doc)?;
+        self.definition_response(
+            get_definition_token_range(line, pos),
+            scope_tree.find_candidates(def),
+        )
+    }
+
+    pub fn goto_declaration(
+        &self,
+        params: GotoDeclarationParams,
+    ) -> Option<GotoDeclarationResponse> {
+        let doc = params.text_document_position_params.text_document.uri;
+        let pos = params.text_document_position_params.position;
+        let file_id = self.srcs.get_id(&doc).to_owned();
+        self.srcs.wait_parse_ready(file_id, false);
+        let file = self.srcs.get_file(file_id)?;
+        let text = file.read().ok()?.text.clone();
+        let line = text.line(pos.line as usize);
+        let path = get_definition_path(line, pos);
+        debug!("goto declaration, path: {:?}", &path);
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let scope_tree = scope_tree.as_ref()?;
+        let def = scope_tree.find_path(&path, text.pos_to_byte(&pos), &doc)?;
+        self.definition_response(
+            get_definition_token_range(line, pos),
+            scope_tree.find_declarations(def),
+        )
+    }
+
+    /// the response to goto definition, declaration, type definition or implementation,
+    /// LocationLinks if the client supports them, otherwise Locations
+    fn definition_response(
+        &self,
+        origin_selection_range: Range,
+        defs: Vec<&dyn Definition>,
+    ) -> Option<GotoDefinitionResponse> {
+        // the definitions may be in other files, so their ranges are computed with the text of
+        // the file they're in, the callers mustn't hold a lock on any file
+        let links: Vec<LocationLink> = defs
+            .into_iter()
+            .filter_map(|x| {
+                let (target_range, target_selection_range) = self.srcs.def_ranges(x)?;
+                Some(LocationLink {
+                    origin_selection_range: Some(origin_selection_range),
+                    target_uri: x.url(),
+                    target_range,
+                    target_selection_range,
+                })
+            })
+            .collect();
+        debug!("defs: {:?}", links);
+        if links.is_empty() {
+            return None;
+        }
+        if self.definition_link_support.load(Ordering::Relaxed) {
+            return Some(GotoDefinitionResponse::Link(links));
+        }
+        let mut locations: Vec<Location> = links
+            .into_iter()
+            .map(|x| Location::new(x.target_uri, x.target_selection_range))
+            .collect();
+        match locations.len() {
+            1 => Some(GotoDefinitionResponse::Scalar(locations.remove(0))),
+            _ => Some(GotoDefinitionResponse::Array(locations)),
+        }
+    }
+
+    pub fn goto_type_definition(
+        &self,
+        params: GotoTypeDefinitionParams,
+    ) -> Option<GotoTypeDefinitionResponse> {
+        let doc = params.text_document_position_params.text_document.uri;
+        let pos = params.text_document_position_params.position;
+        let file_id = self.srcs.get_id(&doc).to_owned();
+        self.srcs.wait_parse_ready(file_id, false);
+        let file = self.srcs.get_file(file_id)?;
+        let text = file.read().ok()?.text.clone();
+        let line = text.line(pos.line as usize);
+        let path = get_definition_path(line, pos);
+        debug!("goto type definition, path: {:?}", &path);
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let scope_tree = scope_tree.as_ref()?;
+        let def = scope_tree.find_path(&path, text.pos_to_byte(&pos), &doc)?;
+        let type_def = scope_tree.find_type(def)?;
+        self.definition_response(get_definition_token_range(line, pos), vec![type_def])
+    }
+
+    pub fn goto_implementation(
+        &self,
+        params: GotoImplementationParams,
+    ) -> Option<GotoImplementationResponse> {
+        let doc = params.text_document_position_params.text_document.uri;
+        let pos = params.text_document_position_params.position;
+        let file_id = self.srcs.get_id(&doc).to_owned();
+        self.srcs.wait_parse_ready(file_id, false);
+        let file = self.srcs.get_file(file_id)?;
+        let text = file.read().ok()?.text.clone();
+        let line = text.line(pos.line as usize);
+        let path = get_definition_path(line, pos);
+        debug!("goto implementation, path: {:?}", &path);
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let scope_tree = scope_tree.as_ref()?;
+        let def = scope_tree.find_path(&path, text.pos_to_byte(&pos), &doc)?;
+        self.definition_response(
+            get_definition_token_range(line, pos),
+            scope_tree.find_implementations(def),
+        )
     }
 
     pub fn hover(&self, params: HoverParams) -> Option<Hover> {
@@ -42,17 +156,30 @@ impl LSPServer {
         self.srcs.wait_parse_ready(file_id, false);
         let file = self.srcs.get_file(file_id)?;
         let file = file.read().ok()?;
-        let token = get_definition_token(file.text.line(pos.line as usize), pos);
-        debug!("hover, token: {}", &token);
+        let path = get_definition_path(file.text.line(pos.line as usize), pos);
+        debug!("hover, path: {:?}", &path);
         let scope_tree = self.srcs.scope_tree.read().ok()?;
         let def = scope_tree
             .as_ref()?
-            .get_definition(&token, file.text.pos_to_byte(&pos), &doc)?;
-        let def_line = file.text.byte_to_line(def.byte_idx());
+            .find_path(&path, file.text.pos_to_byte(&pos), &doc)?;
+        // the definition may be in another file
+        let def_text = match def.url() == doc {
+            true => file.text.clone(),
+            false => self.srcs.get_text(&def.url())?,
+        };
+        let mut value = def_hover(scope_tree.as_ref()?, def, &def_text);
+        if def.def_type() == DefinitionType::Macro {
+            let include_dirs = self.srcs.include_dirs.read().ok()?;
+            let byte_idx = file.text.pos_to_byte(&pos);
+            if let Some(expansion) = expand_macro(&file.text, &doc, byte_idx, &include_dirs) {
+                value.push_str("\n\n// expands to\n");
+                value.push_str(&expansion);
+            }
+        }
         Some(Hover {
             contents: HoverContents::Scalar(MarkedString::LanguageString(LanguageString {
                 language: "systemverilog".to_owned(),
-                value: get_hover(&file.text, def_line),
+                value,
             })),
             range: None,
         })
@@ -80,8 +207,39 @@ impl LSPServer {
         self.srcs.wait_parse_ready(file_id, false);
         let file = self.srcs.get_file(file_id)?;
         let file = file.read().ok()?;
-        let token = get_definition_token(file.text.line(pos.line as usize), pos);
+        let path = get_definition_path(file.text.line(pos.line as usize), pos);
         let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let syntax_tree = file.syntax_tree.as_ref()?;
+        // highlight the identifiers which resolve to the same definition as the one under the
+        // cursor, this handles hierarchical and package scoped names
+        if let Some(def) = scope_tree
+            .as_ref()?
+            .find_path(&path, file.text.pos_to_byte(&pos), &uri)
+        {
+            let same_def =
+                |x: &dyn Definition| x.byte_idx() == def.byte_idx() && x.url() == def.url();
+            return Some(
+                all_identifiers(syntax_tree, &def.ident())
+                    .into_iter()
+                    .filter(|(_, byte_idx)| {
+                        let pos = file.text.byte_to_pos(*byte_idx);
+                        let path = get_definition_path(file.text.line(pos.line as usize), pos);
+                        scope_tree
+                            .as_ref()
+                            .and_then(|x| x.find_path(&path, *byte_idx, &uri))
+                            .is_some_and(same_def)
+                    })
+                    .map(|(ident, byte_idx)| DocumentHighlight {
+                        range: Range::new(
+                            file.text.byte_to_pos(byte_idx),
+                            file.text.byte_to_pos(byte_idx + ident.len()),
+                        ),
+                        kind: None,
+                    })
+                    .collect(),
+            );
+        }
+        let token = get_definition_token(file.text.line(pos.line as usize), pos);
         // use the byte_idx of the definition if possible, otherwise use the cursor
         let byte_idx =
             match scope_tree
@@ -91,7 +249,6 @@ impl LSPServer {
                 Some(def) => def.byte_idx,
                 None => file.text.pos_to_byte(&pos),
             };
-        let syntax_tree = file.syntax_tree.as_ref()?;
         let references = all_identifiers(syntax_tree, &token);
         Some(
             scope_tree
@@ -103,11 +260,19 @@ impl LSPServer {
 
 /// return all identifiers in a syntax tree matching a given token
 fn all_identifiers(syntax_tree: &SyntaxTree, token: &str) -> Vec<(String, usize)> {
+    matching_identifiers(syntax_tree, |x| x == token)
+}
+
+/// return all identifiers in a syntax tree accepted by a predicate
+pub fn matching_identifiers<F>(syntax_tree: &SyntaxTree, matches: F) -> Vec<(String, usize)>
+where
+    F: Fn(&str) -> bool,
+{
     let mut idents: Vec<(String, usize)> = Vec::new();
     for node in syntax_tree {
         if let RefNode::Identifier(_) = node {
             let (ident, byte_idx) = get_ident(syntax_tree, node);
-            if ident == token {
+            if matches(&ident) {
                 idents.push((ident, byte_idx));
             }
         }
@@ -115,6 +280,39 @@ fn all_identifiers(syntax_tree: &SyntaxTree, token: &str) -> Vec<(String, usize)
     idents
 }
 
+/// return the identifiers of all subroutines called in a syntax tree, for a hierarchical or
+/// method call this is the last identifier, ex. m in `h.m(1)`
+pub fn subroutine_calls(syntax_tree: &SyntaxTree) -> Vec<(String, usize)> {
+    let mut calls: Vec<(String, usize)> = Vec::new();
+    for node in syntax_tree {
+        match node {
+            RefNode::TfCall(x) => {
+                let ident = (&x.nodes.0)
+                    .into_iter()
+                    .filter(|x| matches!(x, RefNode::Identifier(_)))
+                    .last();
+                if let Some(ident) = ident {
+                    calls.push(get_ident(syntax_tree, ident));
+                }
+            }
+            RefNode::MethodCallBodyUser(x) => {
+                calls.push(get_ident(
+                    syntax_tree,
+                    RefNode::MethodIdentifier(&x.nodes.0),
+                ));
+            }
+            // method calls through a handle, ex. h.m(1), are parsed as array method calls
+            RefNode::ArrayManipulationCall(x) => {
+                if let ArrayMethodName::MethodIdentifier(ident) = &x.nodes.0 {
+                    calls.push(get_ident(syntax_tree, RefNode::MethodIdentifier(ident)));
+                }
+            }
+            _ => (),
+        }
+    }
+    calls
+}
+
 /// retrieve the token the user invoked goto definition or hover on
 fn get_definition_token(line: RopeSlice, pos: Position) -> String {
     let mut token = String::new();
@@ -140,6 +338,92 @@ fn get_definition_token(line: RopeSlice, pos: Position) -> String {
     token
 }
 
+/// the range of the token the user invoked goto definition or hover on
+fn get_definition_token_range(line: RopeSlice, pos: Position) -> Range {
+    let chars: Vec<char> = line.chars().collect();
+    let is_ident = |c: &char| c.is_alphanumeric() || *c == '_';
+    let idx = line.utf16_cu_to_char(pos.character as usize);
+    let start = chars[..idx]
+        .iter()
+        .rev()
+        .take_while(|x| is_ident(x))
+        .count();
+    let end = chars[idx..].iter().take_while(|x| is_ident(x)).count();
+    let character = |char_idx: usize| line.char_to_utf16_cu(char_idx) as u32;
+    Range::new(
+        Position::new(pos.line, character(idx - start)),
+        Position::new(pos.line, character(idx + end)),
+    )
+}
+
+/// retrieve the hierarchical name the user invoked goto definition or hover on, as a path of
+/// identifiers ending in the token under the cursor, ex. `g_lane[2].sig` becomes [g_lane, sig].
+/// Package and class scopes are followed by a "::" element, ex. `pkg::W` becomes [pkg, ::, W]
+pub fn get_definition_path(line: RopeSlice, pos: Position) -> Vec<String> {
+    let token = get_definition_token(line, pos);
+    let chars: Vec<char> = line.chars().collect();
+    let is_scope = |idx: usize| idx >= 2 && chars[idx - 2] == ':' && chars[idx - 1] == ':';
+    // find the start of the token under the cursor
+    let mut idx = line.utf16_cu_to_char(pos.character as usize);
+    while idx > 0 && (chars[idx - 1].is_alphanumeric() || chars[idx - 1] == '_') {
+        idx -= 1;
+    }
+    let mut end = idx + token.chars().count();
+    while end < chars.len() && chars[end].is_whitespace() {
+        end += 1;
+    }
+    // macro usage, macros aren't hierarchical
+    if idx > 0 && chars[idx - 1] == '`' {
+        return vec![format!("`{}", token)];
+    }
+    let mut path = vec![token];
+    // the token under the cursor is itself a scope
+    if chars.get(end) == Some(&':') && chars.get(end + 1) == Some(&':') {
+        path.push("::".to_owned());
+    }
+    while idx > 0 && (chars[idx - 1] == '.' || is_scope(idx)) {
+        if is_scope(idx) {
+            idx -= 2;
+            path.insert(0, "::".to_owned());
+            let end = idx;
+            while idx > 0 && (chars[idx - 1].is_alphanumeric() || chars[idx - 1] == '_') {
+                idx -= 1;
+            }
+            if idx == end {
+                path.remove(0);
+                break;
+            }
+            path.insert(0, chars[idx..end].iter().collect());
+            continue;
+        }
+        idx -= 1;
+        // skip any selects, ex. [i] or [3:0]
+        while idx > 0 && chars[idx - 1] == ']' {
+            let mut depth = 0;
+            while idx > 0 {
+                idx -= 1;
+                match chars[idx] {
+                    ']' => depth += 1,
+                    '[' => depth -= 1,
+                    _ => (),
+                }
+                if depth == 0 {
+                    break;
+                }
+            }
+        }
+        let end = idx;
+        while idx > 0 && (chars[idx - 1].is_alphanumeric() || chars[idx - 1] == '_') {
+            idx -= 1;
+        }
+        if idx == end {
+            break;
+        }
+        path.insert(0, chars[idx..end].iter().collect());
+    }
+    path
+}
+
 type ScopesAndDefs = Option<(Vec<Box<dyn Scope>>, Vec<Box<dyn Definition>>)>;
 
 /// Take a given syntax node from a sv-parser syntax tree and extract out the definition/scope at
@@ -195,6 +479,12 @@ pub fn match_definitions(
                 scopes.push(Box::new(dec?));
             }
         }
+        RefNode::InterfaceClassDeclaration(n) => {
+            let dec = interface_class_dec(syntax_tree, n, event_iter, url);
+            if dec.is_some() {
+                scopes.push(Box::new(dec?));
+            }
+        }
         RefNode::PortDeclaration(n) => {
             let ports = port_dec_non_ansi(syntax_tree, n, event_iter, url);
             if ports.is_some() {
@@ -223,6 +513,31 @@ pub fn match_definitions(
                 }
             }
         }
+        // class members, the type of which includes their qualifiers such as static
+        RefNode::ClassPropertyNonConst(n) => {
+            let vars = data_dec(syntax_tree, &n.nodes.1, event_iter, url);
+            if let Some(vars) = vars {
+                for var in vars {
+                    match var {
+                        Declaration::Dec(dec) => definitions.push(Box::new(dec)),
+                        Declaration::Import(dec) => definitions.push(Box::new(dec)),
+                        Declaration::Scope(scope) => scopes.push(Box::new(scope)),
+                    }
+                }
+            }
+        }
+        RefNode::ClassMethodFunction(n) => {
+            let dec = function_dec(syntax_tree, &n.nodes.1, event_iter, url);
+            if dec.is_some() {
+                scopes.push(Box::new(dec?));
+            }
+        }
+        RefNode::ClassMethodTask(n) => {
+            let dec = task_dec(syntax_tree, &n.nodes.1, event_iter, url);
+            if dec.is_some() {
+                scopes.push(Box::new(dec?));
+            }
+        }
         RefNode::ParameterDeclaration(n) => {
             let vars = param_dec(syntax_tree, n, event_iter, url);
             if vars.is_some() {
@@ -251,6 +566,65 @@ pub fn match_definitions(
                 scopes.push(Box::new(dec?));
             }
         }
+        RefNode::ClassConstructorDeclaration(n) => {
+            let dec = constructor_dec(syntax_tree, n, event_iter, url);
+            if dec.is_some() {
+                scopes.push(Box::new(dec?));
+            }
+        }
+        RefNode::ClassMethodPureVirtual(n) => {
+            let dec = method_prototype(
+                syntax_tree,
+                &n.nodes.3,
+                event_iter,
+                url,
+                &n.nodes.0,
+                &n.nodes.4,
+            );
+            if dec.is_some() {
+                scopes.push(Box::new(dec?));
+            }
+        }
+        RefNode::ClassMethodExternMethod(n) => {
+            let dec = method_prototype(
+                syntax_tree,
+                &n.nodes.2,
+                event_iter,
+                url,
+                &n.nodes.0,
+                &n.nodes.3,
+            );
+            if dec.is_some() {
+                scopes.push(Box::new(dec?));
+            }
+        }
+        RefNode::InterfaceClassMethod(n) => {
+            let dec = method_prototype(
+                syntax_tree,
+                &n.nodes.2,
+                event_iter,
+                url,
+                &n.nodes.0,
+                &n.nodes.3,
+            );
+            if dec.is_some() {
+                scopes.push(Box::new(dec?));
+            }
+        }
+        RefNode::DpiImportExport(n) => match n {
+            DpiImportExport::ImportFunction(_) | DpiImportExport::ImportTask(_) => {
+                let dec = dpi_import(syntax_tree, n, event_iter, url);
+                if dec.is_some() {
+                    scopes.push(Box::new(dec?));
+                }
+            }
+            _ => {
+                let dec = dpi_export(syntax_tree, n, event_iter, url);
+                if dec.is_some() {
+                    definitions.push(Box::new(dec?));
+                }
+            }
+        },
         RefNode::ModportDeclaration(n) => {
             let decs = modport_dec(syntax_tree, n, event_iter, url);
             if decs.is_some() {
@@ -267,6 +641,14 @@ pub fn match_definitions(
                 }
             }
         }
+        RefNode::GenvarDeclaration(n) => {
+            let decs = genvar_dec(syntax_tree, n, event_iter, url);
+            if decs.is_some() {
+                for dec in decs? {
+                    definitions.push(Box::new(dec));
+                }
+            }
+        }
         RefNode::TextMacroDefinition(n) => {
             let dec = text_macro_def(syntax_tree, n, event_iter, url);
             if dec.is_some() {
@@ -299,9 +681,23 @@ pub fn get_scopes(syntax_tree: &SyntaxTree, url: &Url) -> Option<GenericScope> {
         }
     }
     global_scope.scopes.append(&mut scopes);
+    expand_enum_ranges(&mut global_scope);
     Some(global_scope)
 }
 
+/// the declaration of a definition with its leading comments, and its value if it's a constant
+pub fn def_hover(scope_tree: &GenericScope, def: &dyn Definition, def_text: &Rope) -> String {
+    match def.def_type() {
+        DefinitionType::Macro => macro_definition(def_text, def.byte_idx()),
+        _ => {
+            let def_line = def_text.byte_to_line(def.byte_idx());
+            let mut value = get_hover(def_text, def_line);
+            value.push_str(&value_hover(scope_tree, def));
+            value
+        }
+    }
+}
+
 /// get the hover information
 fn get_hover(doc: &Rope, line: usize) -> String {
     if line == 0 {
@@ -365,6 +761,520 @@ mod tests {
         assert_eq!(token, "ab_c".to_owned());
     }
 
+    #[test]
+    fn test_fuzzy_match() {
+        test_init();
+        assert!(fuzzy_match("rd_ptr_q", "rdp"));
+        assert!(fuzzy_match("wrEn", "wr_e"));
+        assert!(fuzzy_match("data_valid", "dv"));
+        assert!(!fuzzy_match("data_valid", "vd"));
+        assert!(!fuzzy_match("edge", "en"));
+        // many ways to match repeated characters
+        let ident = format!("{}_b", "a_".repeat(60));
+        assert!(fuzzy_match(&ident, &"a".repeat(30)));
+        assert!(!fuzzy_match(&ident, &format!("{}c", "a".repeat(30))));
+    }
+
+    #[test]
+    fn test_definition_path() {
+        test_init();
+        let line = Rope::from_str("assign x = g_lane[i + 1].u_core.sig[3:0];");
+        let path = get_definition_path(line.line(0), Position::new(0, 34));
+        assert_eq!(path, vec!["g_lane", "u_core", "sig"]);
+        let path = get_definition_path(line.line(0), Position::new(0, 12));
+        assert_eq!(path, vec!["g_lane"]);
+        let line = Rope::from_str("assign y = cfg_pkg::cfg_t'(x) + base_pkg::A.b;");
+        let path = get_definition_path(line.line(0), Position::new(0, 22));
+        assert_eq!(path, vec!["cfg_pkg", "::", "cfg_t"]);
+        let path = get_definition_path(line.line(0), Position::new(0, 35));
+        assert_eq!(path, vec!["base_pkg", "::"]);
+        let path = get_definition_path(line.line(0), Position::new(0, 44));
+        assert_eq!(path, vec!["base_pkg", "::", "A", "b"]);
+        let line = Rope::from_str("  `REG(cfg.valid)");
+        let path = get_definition_path(line.line(0), Position::new(0, 4));
+        assert_eq!(path, vec!["`REG"]);
+    }
+
+    #[test]
+    fn test_generate_blocks() {
+        test_init();
+        let text = r#"module test #(parameter N = 4) ();
+  genvar j;
+  for (genvar i = 0; i < N; i++) begin : g_lane
+    logic sig;
+  end
+  if (N > 2) begin
+    logic wide;
+  end else begin : g_narrow
+    logic narrow;
+  end
+  generate
+    case (N)
+      1: begin
+        logic one;
+      end
+    endcase
+  endgenerate
+  assign x = g_lane[0].sig;
+endmodule"#;
+        let doc = Rope::from_str(text);
+        let url = Url::parse("file:///test.sv").unwrap();
+        let syntax_tree = parse(&doc, &url, &None, &Vec::new()).unwrap();
+        let scope_tree = get_scopes(&syntax_tree, &url).unwrap();
+        let module = &scope_tree.scopes[0];
+        let blocks: Vec<String> = module.scopes().iter().map(|x| x.ident()).collect();
+        assert_eq!(blocks, vec!["g_lane", "genblk2", "g_narrow", "genblk3"]);
+        assert!(module.defs().iter().any(|x| x.ident() == "j"));
+        let g_lane = &module.scopes()[0];
+        let idents: Vec<String> = g_lane.defs().iter().map(|x| x.ident()).collect();
+        assert_eq!(idents, vec!["i", "sig"]);
+        assert_eq!(doc.byte_to_pos(g_lane.start()), Position::new(2, 33));
+        assert_eq!(doc.byte_to_pos(g_lane.end()), Position::new(4, 2));
+
+        // names inside a generate block resolve from within the block
+        let def = scope_tree
+            .find_definition("sig", doc.pos_to_byte(&Position::new(3, 4)), &url)
+            .unwrap();
+        assert_eq!(doc.byte_to_pos(def.byte_idx()), Position::new(3, 10));
+        // and through the generate block name from outside of it
+        let path = get_definition_path(doc.line(17), Position::new(17, 24));
+        let def = scope_tree
+            .find_path(&path, doc.pos_to_byte(&Position::new(17, 24)), &url)
+            .unwrap();
+        assert_eq!(doc.byte_to_pos(def.byte_idx()), Position::new(3, 10));
+    }
+
+    #[test]
+    fn test_struct_member_definition() {
+        test_init();
+        let text = r#"package my_pkg;
+  typedef struct packed {
+    logic [31:0] addr;
+  } req_t;
+endpackage
+module test;
+  my_pkg::req_t reqs [2];
+  assign x = reqs[1].addr;
+endmodule"#;
+        let doc = Rope::from_str(text);
+        let url = Url::parse("file:///test.sv").unwrap();
+        let syntax_tree = parse(&doc, &url, &None, &Vec::new()).unwrap();
+        let scope_tree = get_scopes(&syntax_tree, &url).unwrap();
+        let pos = Position::new(7, 22);
+        let path = get_definition_path(doc.line(7), pos);
+        assert_eq!(path, vec!["reqs", "addr"]);
+        let def = scope_tree
+            .find_path(&path, doc.pos_to_byte(&pos), &url)
+            .unwrap();
+        assert_eq!(doc.byte_to_pos(def.byte_idx()), Position::new(2, 17));
+    }
+
+    #[test]
+    fn test_type_definition() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let uri2 = Url::parse("file:///types.sv").unwrap();
+        let text = r#"module test;
+  import types_pkg::*;
+  types_pkg::req_t req;
+  state_t state;
+  logic plain;
+  core u_core ();
+endmodule
+module other;
+  import types_pkg::req_t;
+  state_t state;
+endmodule
+"#;
+        let text2 = r#"package types_pkg;
+  typedef struct packed {
+    logic valid;
+  } req_t;
+  typedef enum {IDLE, BUSY} state_t;
+endpackage
+module core ();
+endmodule
+"#;
+        for (uri, text) in &[(&uri, text), (&uri2, text2)] {
+            server.did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: (*uri).clone(),
+                    language_id: "systemverilog".to_owned(),
+                    version: 0,
+                    text: text.to_string(),
+                },
+            });
+            let fid = server.srcs.get_id(uri);
+            server.srcs.wait_parse_ready(fid, true);
+        }
+        let type_def = |position: Position| {
+            let params = GotoTypeDefinitionParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position,
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            match server.goto_type_definition(params)? {
+                GotoTypeDefinitionResponse::Scalar(location) => {
+                    Some((location.uri, location.range))
+                }
+                _ => None,
+            }
+        };
+        assert_eq!(
+            type_def(Position::new(2, 20)),
+            Some((
+                uri2.clone(),
+                Range::new(Position::new(3, 4), Position::new(3, 9))
+            ))
+        );
+        let type_def =
+            |position: Position| type_def(position).map(|(uri, range)| (uri, range.start));
+        assert_eq!(
+            type_def(Position::new(3, 11)),
+            Some((uri2.clone(), Position::new(4, 28)))
+        );
+        assert_eq!(
+            type_def(Position::new(5, 8)),
+            Some((uri2.clone(), Position::new(6, 7)))
+        );
+        assert_eq!(type_def(Position::new(4, 10)), None);
+        // types_pkg::state_t is not imported into other
+        assert_eq!(type_def(Position::new(9, 11)), None);
+    }
+
+    #[test]
+    fn test_definition_links() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let uri2 = Url::parse("file:///lib_a.sv").unwrap();
+        let uri3 = Url::parse("file:///lib_b.sv").unwrap();
+        let text = r#"module test;
+  logic a;
+  fifo u_fifo ();
+  adder u_adder (.a(a));
+endmodule
+"#;
+        let text2 = r#"// first library
+
+module fifo ();
+endmodule
+module adder (input a);
+endmodule
+"#;
+        let text3 = r#"module fifo ();
+endmodule
+"#;
+        for (uri, text) in &[(&uri, text), (&uri2, text2), (&uri3, text3)] {
+            server.did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: (*uri).clone(),
+                    language_id: "systemverilog".to_owned(),
+                    version: 0,
+                    text: text.to_string(),
+                },
+            });
+            let fid = server.srcs.get_id(uri);
+            server.srcs.wait_parse_ready(fid, true);
+        }
+        let goto = |position: Position| {
+            server.goto_definition(GotoDefinitionParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position,
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+        };
+        // positions are computed with the text of the file the definition is in
+        assert_eq!(
+            goto(Position::new(3, 4)),
+            Some(GotoDefinitionResponse::Scalar(Location::new(
+                uri2.clone(),
+                Range::new(Position::new(4, 7), Position::new(4, 12))
+            )))
+        );
+        // a module defined in two libraries
+        assert_eq!(
+            goto(Position::new(2, 3)),
+            Some(GotoDefinitionResponse::Array(vec![
+                Location::new(
+                    uri2.clone(),
+                    Range::new(Position::new(2, 7), Position::new(2, 11))
+                ),
+                Location::new(
+                    uri3.clone(),
+                    Range::new(Position::new(0, 7), Position::new(0, 11))
+                ),
+            ]))
+        );
+
+        server
+            .definition_link_support
+            .store(true, Ordering::Relaxed);
+        assert_eq!(
+            goto(Position::new(3, 4)),
+            Some(GotoDefinitionResponse::Link(vec![LocationLink {
+                origin_selection_range: Some(Range::new(Position::new(3, 2), Position::new(3, 7))),
+                target_uri: uri2.clone(),
+                target_range: Range::new(Position::new(4, 0), Position::new(5, 0)),
+                target_selection_range: Range::new(Position::new(4, 7), Position::new(4, 12)),
+            }]))
+        );
+    }
+
+    #[test]
+    fn test_declaration() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let text = r#"typedef class driver;
+import "DPI-C" function int c_add(input int a, input int b);
+// DPI
+class driver;
+  extern function void drive(int value);
+endclass
+function void driver::drive(int value);
+endfunction
+module test;
+  driver drv;
+  function int sv_add(int a, int b);
+    return a + b;
+  endfunction
+  export "DPI-C" function sv_add;
+  initial begin
+    drv.drive(c_add(1, 2));
+  end
+endmodule
+"#;
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+        let fid = server.srcs.get_id(&uri);
+        server.srcs.wait_parse_ready(fid, true);
+        let position = |line, character| TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            position: Position::new(line, character),
+        };
+        let declaration = |line, character| {
+            let params = GotoDeclarationParams {
+                text_document_position_params: position(line, character),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            match server.goto_declaration(params)? {
+                GotoDeclarationResponse::Scalar(location) => Some(location.range.start),
+                _ => None,
+            }
+        };
+        let definition = |line, character| {
+            let params = GotoDefinitionParams {
+                text_document_position_params: position(line, character),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            match server.goto_definition(params)? {
+                GotoDefinitionResponse::Scalar(location) => Some(location.range.start),
+                _ => None,
+            }
+        };
+        // DPI import
+        assert_eq!(declaration(15, 15), Some(Position::new(1, 28)));
+        assert_eq!(definition(15, 15), Some(Position::new(1, 28)));
+        // extern method, declared by its prototype and defined by its body
+        assert_eq!(declaration(15, 9), Some(Position::new(4, 23)));
+        assert_eq!(definition(15, 9), Some(Position::new(6, 22)));
+        assert_eq!(declaration(6, 23), Some(Position::new(4, 23)));
+        // forward typedef of a class
+        assert_eq!(declaration(9, 3), Some(Position::new(0, 14)));
+        assert_eq!(definition(9, 3), Some(Position::new(3, 6)));
+        // the function isn't resolved to its DPI export
+        assert_eq!(definition(13, 28), Some(Position::new(10, 15)));
+    }
+
+    #[test]
+    fn test_implementation() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let text = r#"interface class drivable;
+  pure virtual function void drive(int value);
+endclass
+virtual class base_driver implements drivable;
+  extern virtual function void drive(int value);
+  pure virtual task run();
+endclass
+function void base_driver::drive(int value);
+endfunction
+class axi_driver extends base_driver;
+  virtual function void drive(int value);
+  endfunction
+  virtual task run();
+  endtask
+endclass
+package other_pkg;
+  virtual class base_driver;
+    pure virtual task run();
+  endclass
+  class usb_driver extends base_driver;
+    virtual task run();
+    endtask
+  endclass
+endpackage
+"#;
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+        let fid = server.srcs.get_id(&uri);
+        server.srcs.wait_parse_ready(fid, true);
+        let implementations = |position: Position| -> Vec<Range> {
+            let params = GotoImplementationParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position,
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            match server.goto_implementation(params) {
+                Some(GotoImplementationResponse::Scalar(location)) => vec![location.range],
+                Some(GotoImplementationResponse::Array(locations)) => {
+                    locations.into_iter().map(|x| x.range).collect()
+                }
+                _ => Vec::new(),
+            }
+        };
+        // the whole identifier of the implementation
+        assert_eq!(
+            implementations(Position::new(5, 21)),
+            vec![Range::new(Position::new(12, 15), Position::new(12, 18))]
+        );
+        let implementations = |position: Position| -> Vec<Position> {
+            implementations(position)
+                .into_iter()
+                .map(|x| x.start)
+                .collect()
+        };
+        assert_eq!(
+            implementations(Position::new(1, 30)),
+            vec![
+                Position::new(4, 31),
+                Position::new(7, 27),
+                Position::new(10, 24)
+            ]
+        );
+        assert_eq!(
+            implementations(Position::new(4, 32)),
+            vec![Position::new(7, 27), Position::new(10, 24)]
+        );
+        assert_eq!(
+            implementations(Position::new(5, 21)),
+            vec![Position::new(12, 15)]
+        );
+        assert_eq!(
+            implementations(Position::new(0, 17)),
+            vec![Position::new(3, 14), Position::new(9, 6)]
+        );
+        // only the subclasses of the base_driver outside of other_pkg
+        assert_eq!(
+            implementations(Position::new(3, 14)),
+            vec![Position::new(9, 6)]
+        );
+        assert_eq!(
+            implementations(Position::new(17, 22)),
+            vec![Position::new(20, 17)]
+        );
+    }
+
+    #[test]
+    fn test_inherited_members() {
+        test_init();
+        let text = r#"package base_pkg;
+  class component;
+    int id;
+  endclass
+endpackage
+class driver extends base_pkg::component;
+  function int get_id();
+    return id;
+  endfunction
+endclass
+module test;
+  driver drv;
+  initial drv.id = 2;
+endmodule"#;
+        let doc = Rope::from_str(text);
+        let url = Url::parse("file:///test.sv").unwrap();
+        let syntax_tree = parse(&doc, &url, &None, &Vec::new()).unwrap();
+        let scope_tree = get_scopes(&syntax_tree, &url).unwrap();
+        let find = |pos: Position| {
+            let path = get_definition_path(doc.line(pos.line as usize), pos);
+            let def = scope_tree.find_path(&path, doc.pos_to_byte(&pos), &url)?;
+            Some(doc.byte_to_pos(def.byte_idx()))
+        };
+        assert_eq!(find(Position::new(12, 15)), Some(Position::new(2, 8)));
+        assert_eq!(find(Position::new(7, 12)), Some(Position::new(2, 8)));
+    }
+
+    #[test]
+    fn test_hierarchical_names() {
+        test_init();
+        let text = r#"package cfg_pkg;
+  parameter int WIDTH = 8;
+endpackage
+interface bus_if;
+  logic valid;
+  modport mst(output valid);
+endinterface
+module alu(bus_if.mst port);
+  logic [7:0] result;
+  assign port.valid = 1;
+endmodule
+module core(bus_if.mst port);
+  alu u_alu(.port(port));
+endmodule
+module top;
+  logic [cfg_pkg::WIDTH-1:0] data;
+  bus_if intf();
+  core u_core(.port(intf));
+  assign data = top.u_core.u_alu.result;
+  assign intf.mst.valid = 0;
+endmodule"#;
+        let doc = Rope::from_str(text);
+        let url = Url::parse("file:///test.sv").unwrap();
+        let syntax_tree = parse(&doc, &url, &None, &Vec::new()).unwrap();
+        let scope_tree = get_scopes(&syntax_tree, &url).unwrap();
+        let find = |pos: Position| {
+            let path = get_definition_path(doc.line(pos.line as usize), pos);
+            let def = scope_tree.find_path(&path, doc.pos_to_byte(&pos), &url)?;
+            Some(doc.byte_to_pos(def.byte_idx()))
+        };
+        // package scoped parameter, and the package itself
+        assert_eq!(find(Position::new(15, 20)), Some(Position::new(1, 16)));
+        assert_eq!(find(Position::new(15, 10)), Some(Position::new(0, 8)));
+        // absolute hierarchical name through module instances
+        assert_eq!(find(Position::new(18, 34)), Some(Position::new(8, 14)));
+        assert_eq!(find(Position::new(18, 27)), Some(Position::new(12, 6)));
+        // signals through a modport, of an interface instance and of a modport port
+        assert_eq!(find(Position::new(19, 19)), Some(Position::new(4, 8)));
+        assert_eq!(find(Position::new(9, 15)), Some(Position::new(4, 8)));
+    }
+
     #[test]
     fn test_get_definition() {
         test_init();
@@ -496,4 +1406,58 @@ endmodule"#;
         ];
         assert_eq!(highlights, expected)
     }
+
+    #[test]
+    fn test_hierarchical_highlight() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let text = r#"module alu;
+  logic result;
+endmodule
+module top;
+  logic result;
+  alu u_alu();
+  assign result = u_alu.result;
+  assign top.result = 0;
+endmodule
+"#;
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+        let fid = server.srcs.get_id(&uri);
+        server.srcs.wait_parse_ready(fid, true);
+        let highlight = |position: Position| {
+            let params = DocumentHighlightParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position,
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            let highlights = server.document_highlight(params).unwrap();
+            highlights
+                .into_iter()
+                .map(|x| x.range.start)
+                .collect::<Vec<Position>>()
+        };
+        assert_eq!(
+            highlight(Position::new(6, 26)),
+            vec![Position::new(1, 8), Position::new(6, 24)]
+        );
+        assert_eq!(
+            highlight(Position::new(4, 8)),
+            vec![
+                Position::new(4, 8),
+                Position::new(6, 9),
+                Position::new(7, 13)
+            ]
+        );
+    }
 }
@@ -0,0 +1,933 @@
+use crate::definition::def_types::*;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use tower_lsp::lsp_types::*;
+
+// maximum depth of parameter references followed while evaluating, guards against cycles
+const MAX_DEPTH: usize = 32;
+// maximum width of a concatenation, wider values can't be held without losing bits
+const MAX_WIDTH: u32 = 127;
+
+/// The value of a constant expression, along with its bit width if it is known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConstValue {
+    pub value: i128,
+    pub width: Option<u32>,
+}
+
+impl ConstValue {
+    pub fn new(value: i128, width: Option<u32>) -> Self {
+        Self { value, width }
+    }
+
+    /// format the value for display in decimal and hex, negative values in hex as their two's
+    /// complement at their width, ex. `-1 (0xFF)` for a byte
+    pub fn display(&self) -> String {
+        match self.width {
+            _ if self.value >= 0 => format!("{} (0x{:X})", self.value, self.value),
+            Some(width) if width < MAX_WIDTH => {
+                format!("{} (0x{:X})", self.value, self.value & mask(width))
+            }
+            _ => self.value.to_string(),
+        }
+    }
+}
+
+/// Provides values and widths of identifiers referenced in a constant expression
+pub trait ConstEnv {
+    // value of the parameter with the given identifier
+    fn value(&self, package: Option<&str>, ident: &str) -> Option<ConstValue>;
+    // width of the parameter, variable or type with the given identifier
+    fn width(&self, package: Option<&str>, ident: &str) -> Option<u32>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(ConstValue),
+    Ident(String),
+    Sym(&'static str),
+}
+
+// operator symbols, longest first so that they are matched greedily
+const SYMBOLS: &[&str] = &[
+    "<<<", ">>>", "===", "!==", "**", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "~&", "~|",
+    "~^", "^~", "::", "+", "-", "*", "/", "%", "<", ">", "!", "~", "&", "|", "^", "?", ":", "(",
+    ")", "{", "}", "[", "]", ",", ".",
+];
+
+// builtin types and their widths, vector types are widened by their packed dimensions
+const BUILTIN_TYPES: &[(&str, u32)] = &[
+    ("bit", 1),
+    ("logic", 1),
+    ("reg", 1),
+    ("wire", 1),
+    ("byte", 8),
+    ("shortint", 16),
+    ("int", 32),
+    ("integer", 32),
+    ("longint", 64),
+    ("time", 64),
+];
+
+// builtin types which are signed unless declared unsigned
+const SIGNED_TYPES: &[&str] = &["byte", "shortint", "int", "integer", "longint"];
+
+fn builtin_width(ident: &str) -> Option<u32> {
+    BUILTIN_TYPES
+        .iter()
+        .find(|(name, _)| *name == ident)
+        .map(|(_, width)| *width)
+}
+
+/// parse the digits of a based number, ex. the `ff` of `8'hff`
+fn parse_based(digits: &str, radix: u32) -> Option<i128> {
+    let digits: String = digits.chars().filter(|c| *c != '_').collect();
+    if digits.is_empty() {
+        return None;
+    }
+    i128::from_str_radix(&digits, radix).ok()
+}
+
+fn tokenize(expr: &str) -> Option<Vec<Token>> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '\'' {
+            // size of a sized literal, or the value of a decimal literal
+            let mut size = String::new();
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '_') {
+                size.push(chars[i]);
+                i += 1;
+            }
+            // the tokens captured from the syntax tree are space separated, so the base
+            // specifier of a sized literal may be separated from its size
+            let mut j = i;
+            while j < chars.len() && chars[j].is_whitespace() {
+                j += 1;
+            }
+            if j < chars.len() && chars[j] == '\'' {
+                i = j + 1;
+                if i < chars.len() && (chars[i] == 's' || chars[i] == 'S') {
+                    i += 1;
+                }
+                let radix = match chars.get(i)?.to_ascii_lowercase() {
+                    'b' => 2,
+                    'o' => 8,
+                    'd' => 10,
+                    'h' => 16,
+                    // unbased unsized literals
+                    '0' if size.is_empty() => {
+                        i += 1;
+                        tokens.push(Token::Num(ConstValue::new(0, None)));
+                        continue;
+                    }
+                    _ => return None,
+                };
+                i += 1;
+                while i < chars.len() && chars[i].is_whitespace() {
+                    i += 1;
+                }
+                let mut digits = String::new();
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    digits.push(chars[i]);
+                    i += 1;
+                }
+                let width = if size.is_empty() {
+                    None
+                } else {
+                    Some(u32::try_from(parse_based(&size, 10)?).ok()?)
+                };
+                tokens.push(Token::Num(ConstValue::new(
+                    parse_based(&digits, radix)?,
+                    width,
+                )));
+            } else {
+                // real numbers can't be evaluated
+                if i < chars.len() && (chars[i] == '.' || chars[i] == 'e' || chars[i] == 'E') {
+                    return None;
+                }
+                tokens.push(Token::Num(ConstValue::new(parse_based(&size, 10)?, None)));
+            }
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let mut ident = String::new();
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+            {
+                ident.push(chars[i]);
+                i += 1;
+            }
+            tokens.push(Token::Ident(ident));
+        } else {
+            let rest: String = chars[i..].iter().take(3).collect();
+            let sym = SYMBOLS.iter().find(|x| rest.starts_with(*x))?;
+            i += sym.len();
+            tokens.push(Token::Sym(sym));
+        }
+    }
+    Some(tokens)
+}
+
+/// the number of bits from msb to lsb inclusive, ex. 8 for [7:0] or [0:7]
+fn range_size(msb: i128, lsb: i128) -> Option<u32> {
+    u32::try_from(msb.checked_sub(lsb)?.unsigned_abs())
+        .ok()?
+        .checked_add(1)
+}
+
+fn mask(width: u32) -> i128 {
+    if width >= 127 {
+        i128::MAX
+    } else {
+        (1 << width) - 1
+    }
+}
+
+fn max_width(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (Some(a), None) | (None, Some(a)) => Some(a),
+        (None, None) => None,
+    }
+}
+
+fn clog2(value: i128) -> i128 {
+    let mut result = 0;
+    while result < 126 && (1i128 << result) < value {
+        result += 1;
+    }
+    result
+}
+
+// binary operator precedence, higher binds tighter
+fn precedence(op: &str) -> Option<u8> {
+    match op {
+        "**" => Some(11),
+        "*" | "/" | "%" => Some(10),
+        "+" | "-" => Some(9),
+        "<<" | ">>" | "<<<" | ">>>" => Some(8),
+        "<" | "<=" | ">" | ">=" => Some(7),
+        "==" | "!=" | "===" | "!==" => Some(6),
+        "&" => Some(5),
+        "^" | "~^" | "^~" => Some(4),
+        "|" => Some(3),
+        "&&" => Some(2),
+        "||" => Some(1),
+        _ => None,
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    env: &'a dyn ConstEnv,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn is_sym(&self, sym: &str) -> bool {
+        matches!(self.peek(), Some(Token::Sym(x)) if *x == sym)
+    }
+
+    fn expect(&mut self, sym: &str) -> Option<()> {
+        if self.is_sym(sym) {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn expr(&mut self) -> Option<ConstValue> {
+        let cond = self.binary(0)?;
+        if self.is_sym("?") {
+            self.pos += 1;
+            let a = self.expr()?;
+            self.expect(":")?;
+            let b = self.expr()?;
+            Some(if cond.value != 0 { a } else { b })
+        } else {
+            Some(cond)
+        }
+    }
+
+    fn binary(&mut self, min_prec: u8) -> Option<ConstValue> {
+        let mut lhs = self.unary()?;
+        while let Some(Token::Sym(op)) = self.peek() {
+            let op = *op;
+            let prec = match precedence(op) {
+                Some(prec) if prec > min_prec => prec,
+                _ => break,
+            };
+            self.pos += 1;
+            // ** is right associative
+            let rhs = if op == "**" {
+                self.binary(prec - 1)?
+            } else {
+                self.binary(prec)?
+            };
+            lhs = binary_op(op, lhs, rhs)?;
+        }
+        Some(lhs)
+    }
+
+    fn unary(&mut self) -> Option<ConstValue> {
+        let op = match self.peek() {
+            Some(Token::Sym(op)) if ["+", "-", "!", "~", "&", "|", "^"].contains(op) => *op,
+            _ => return self.postfix(),
+        };
+        self.pos += 1;
+        let x = self.unary()?;
+        let width = x.width.unwrap_or(32);
+        Some(match op {
+            "+" => x,
+            "-" => ConstValue::new(x.value.checked_neg()?, x.width),
+            "!" => ConstValue::new((x.value == 0) as i128, Some(1)),
+            "~" => ConstValue::new(!x.value & mask(width), x.width),
+            "&" => ConstValue::new((x.value & mask(width) == mask(width)) as i128, Some(1)),
+            "|" => ConstValue::new((x.value != 0) as i128, Some(1)),
+            "^" => ConstValue::new(((x.value & mask(width)).count_ones() % 2) as i128, Some(1)),
+            _ => unreachable!(),
+        })
+    }
+
+    fn postfix(&mut self) -> Option<ConstValue> {
+        let mut x = self.primary()?;
+        // constant bit and part selects
+        while self.is_sym("[") {
+            self.pos += 1;
+            let msb = self.expr()?;
+            let lsb = if self.is_sym(":") {
+                self.pos += 1;
+                self.expr()?
+            } else {
+                msb
+            };
+            self.expect("]")?;
+            let width = range_size(msb.value, lsb.value)?;
+            let shift = u32::try_from(msb.value.min(lsb.value)).ok()?;
+            x = ConstValue::new(x.value.checked_shr(shift)? & mask(width), Some(width));
+        }
+        Some(x)
+    }
+
+    fn primary(&mut self) -> Option<ConstValue> {
+        match self.next()? {
+            Token::Num(x) => Some(x),
+            Token::Sym("(") => {
+                let x = self.expr()?;
+                self.expect(")")?;
+                Some(x)
+            }
+            Token::Sym("{") => self.concatenation(),
+            Token::Ident(ident) if ident.starts_with('$') => self.system_call(&ident),
+            Token::Ident(ident) => {
+                if self.is_sym("::") {
+                    self.pos += 1;
+                    match self.next()? {
+                        Token::Ident(member) => self.env.value(Some(&ident), &member),
+                        _ => None,
+                    }
+                } else {
+                    self.env.value(None, &ident)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn concatenation(&mut self) -> Option<ConstValue> {
+        let first = self.expr()?;
+        // replication, ex. {4{1'b1}}
+        if self.is_sym("{") {
+            self.pos += 1;
+            let inner = self.concatenation()?;
+            self.expect("}")?;
+            let width = inner.width?;
+            let count = u32::try_from(first.value).ok()?;
+            // the value must fit without losing any bits
+            let total = width.checked_mul(count)?;
+            if width == 0 || total > MAX_WIDTH {
+                return None;
+            }
+            let mut value: i128 = 0;
+            for _ in 0..count {
+                value = (value << width) | (inner.value & mask(width));
+            }
+            return Some(ConstValue::new(value, Some(total)));
+        }
+        let mut items = vec![first];
+        while self.is_sym(",") {
+            self.pos += 1;
+            items.push(self.expr()?);
+        }
+        self.expect("}")?;
+        let mut value: i128 = 0;
+        let mut width: u32 = 0;
+        for item in items {
+            let item_width = item.width?;
+            width = width.checked_add(item_width)?;
+            if width > MAX_WIDTH {
+                return None;
+            }
+            value = (value << item_width) | (item.value & mask(item_width));
+        }
+        Some(ConstValue::new(value, Some(width)))
+    }
+
+    fn system_call(&mut self, ident: &str) -> Option<ConstValue> {
+        self.expect("(")?;
+        let result = match ident {
+            "$clog2" => ConstValue::new(clog2(self.expr()?.value), Some(32)),
+            "$bits" => ConstValue::new(self.bits()? as i128, Some(32)),
+            "$signed" | "$unsigned" => self.expr()?,
+            _ => return None,
+        };
+        self.expect(")")?;
+        Some(result)
+    }
+
+    /// the argument of $bits, which may be a type or an expression
+    fn bits(&mut self) -> Option<u32> {
+        match self.peek()? {
+            Token::Ident(ident) if builtin_width(ident).is_some() => {
+                let mut width = builtin_width(ident)?;
+                self.pos += 1;
+                if let Some(Token::Ident(sign)) = self.peek() {
+                    if sign == "signed" || sign == "unsigned" {
+                        self.pos += 1;
+                    }
+                }
+                while self.is_sym("[") {
+                    width = width.checked_mul(self.dimension()?)?;
+                }
+                Some(width)
+            }
+            Token::Ident(ident) if !ident.starts_with('$') => {
+                let ident = ident.clone();
+                let start = self.pos;
+                self.pos += 1;
+                let package = if self.is_sym("::") {
+                    self.pos += 1;
+                    match self.next()? {
+                        Token::Ident(member) => Some(member),
+                        _ => return None,
+                    }
+                } else {
+                    None
+                };
+                if self.is_sym(")") {
+                    match &package {
+                        Some(member) => self.env.width(Some(&ident), member),
+                        None => self.env.width(None, &ident),
+                    }
+                } else {
+                    self.pos = start;
+                    self.expr()?.width
+                }
+            }
+            _ => self.expr()?.width,
+        }
+    }
+
+    /// the size of a packed or unpacked dimension, ex. [7:0] or [8]
+    fn dimension(&mut self) -> Option<u32> {
+        self.expect("[")?;
+        let msb = self.expr()?;
+        let size = if self.is_sym(":") {
+            self.pos += 1;
+            let lsb = self.expr()?;
+            range_size(msb.value, lsb.value)?
+        } else {
+            u32::try_from(msb.value).ok()?
+        };
+        self.expect("]")?;
+        Some(size)
+    }
+}
+
+fn binary_op(op: &str, a: ConstValue, b: ConstValue) -> Option<ConstValue> {
+    let width = max_width(a.width, b.width);
+    let bool_val = |x: bool| Some(ConstValue::new(x as i128, Some(1)));
+    let value = match op {
+        "+" => a.value.checked_add(b.value)?,
+        "-" => a.value.checked_sub(b.value)?,
+        "*" => a.value.checked_mul(b.value)?,
+        "/" => a.value.checked_div(b.value)?,
+        "%" => a.value.checked_rem(b.value)?,
+        "**" => a.value.checked_pow(u32::try_from(b.value).ok()?)?,
+        "<<" | "<<<" => {
+            return Some(ConstValue::new(
+                a.value.checked_shl(u32::try_from(b.value).ok()?)?,
+                a.width,
+            ))
+        }
+        ">>" | ">>>" => {
+            return Some(ConstValue::new(
+                a.value.checked_shr(u32::try_from(b.value).ok()?)?,
+                a.width,
+            ))
+        }
+        "<" => return bool_val(a.value < b.value),
+        "<=" => return bool_val(a.value <= b.value),
+        ">" => return bool_val(a.value > b.value),
+        ">=" => return bool_val(a.value >= b.value),
+        "==" | "===" => return bool_val(a.value == b.value),
+        "!=" | "!==" => return bool_val(a.value != b.value),
+        "&&" => return bool_val(a.value != 0 && b.value != 0),
+        "||" => return bool_val(a.value != 0 || b.value != 0),
+        "&" => a.value & b.value,
+        "|" => a.value | b.value,
+        "^" => a.value ^ b.value,
+        "~^" | "^~" => !(a.value ^ b.value) & mask(width.unwrap_or(32)),
+        _ => return None,
+    };
+    Some(ConstValue::new(value, width))
+}
+
+/// evaluate a constant expression, identifiers are resolved through the given environment
+pub fn eval_const_expr(expr: &str, env: &dyn ConstEnv) -> Option<ConstValue> {
+    let mut parser = Parser {
+        tokens: tokenize(expr)?,
+        pos: 0,
+        env,
+    };
+    let result = parser.expr()?;
+    if parser.pos == parser.tokens.len() {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+/// compute the width of a declared type, ex. `logic [7:0]` or `int`
+pub fn type_width(type_str: &str, env: &dyn ConstEnv) -> Option<u32> {
+    let mut parser = Parser {
+        tokens: tokenize(type_str)?,
+        pos: 0,
+        env,
+    };
+    let mut width: Option<u32> = None;
+    while let Some(token) = parser.peek() {
+        match token {
+            Token::Ident(ident) if width.is_none() && builtin_width(ident).is_some() => {
+                width = builtin_width(ident);
+                parser.pos += 1;
+            }
+            Token::Sym("[") => {
+                width = Some(width.unwrap_or(1).checked_mul(parser.dimension()?)?);
+            }
+            _ => parser.pos += 1,
+        }
+    }
+    width
+}
+
+/// whether a declared type is signed, ex. `int` or `logic signed [7:0]`
+fn type_signed(type_str: &str) -> bool {
+    let words: Vec<&str> = type_str
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .collect();
+    !words.contains(&"unsigned")
+        && (words.contains(&"signed") || words.iter().any(|x| SIGNED_TYPES.contains(x)))
+}
+
+/// truncate a value to a width, then extend its sign bit
+fn sign_extend(value: i128, width: u32) -> i128 {
+    if width == 0 || width >= MAX_WIDTH {
+        return value;
+    }
+    let value = value & mask(width);
+    if value >> (width - 1) & 1 == 1 {
+        value - (1 << width)
+    } else {
+        value
+    }
+}
+
+/// split the text of a parameter definition into its declaration and value expression
+pub fn param_expr(type_str: &str) -> Option<(&str, &str)> {
+    let eq_offset = type_str.find('=')?;
+    let expr = type_str[eq_offset + 1..]
+        .trim()
+        .trim_end_matches(&[';', ','][..])
+        .trim();
+    if expr.is_empty() {
+        None
+    } else {
+        Some((&type_str[..eq_offset], expr))
+    }
+}
+
+/// Resolves identifiers in constant expressions through the scope tree
+pub struct ScopeEnv<'a> {
+    scope_tree: &'a GenericScope,
+    url: Url,
+    byte_idx: usize,
+    // parameter values overridden by a module instantiation, ex. #(.WIDTH(8))
+    overrides: &'a HashMap<String, ConstValue>,
+    depth: usize,
+}
+
+impl<'a> ScopeEnv<'a> {
+    /// create an environment for evaluating an expression at the given location
+    pub fn new(
+        scope_tree: &'a GenericScope,
+        url: &Url,
+        byte_idx: usize,
+        overrides: &'a HashMap<String, ConstValue>,
+    ) -> Self {
+        Self {
+            scope_tree,
+            url: url.clone(),
+            byte_idx,
+            overrides,
+            depth: 0,
+        }
+    }
+
+    fn find(&self, package: Option<&str>, ident: &str) -> Option<&'a dyn Definition> {
+        self.scope_tree
+            .find_qualified(package, ident, self.byte_idx, &self.url)
+    }
+
+    /// environment for evaluating an expression found at a definition
+    fn at(&self, def: &dyn Definition) -> Option<ScopeEnv<'a>> {
+        if self.depth >= MAX_DEPTH {
+            return None;
+        }
+        Some(ScopeEnv {
+            scope_tree: self.scope_tree,
+            url: def.url(),
+            byte_idx: def.byte_idx(),
+            overrides: self.overrides,
+            depth: self.depth + 1,
+        })
+    }
+
+    /// compute the value of a parameter, localparam or enum literal definition
+    pub fn param_value(&self, def: &dyn Definition) -> Option<ConstValue> {
+        if let Some(value) = self.overrides.get(&def.ident()) {
+            return Some(*value);
+        }
+        let type_str = def.type_str();
+        let (decl, expr) = param_expr(&type_str)?;
+        let env = self.at(def)?;
+        let value = eval_const_expr(expr, &env)?;
+        // an explicit type determines the width of the parameter
+        let decl = clean_type_str(decl, &def.ident());
+        match type_width(&decl, &env) {
+            Some(width) if type_signed(&decl) => Some(ConstValue::new(
+                sign_extend(value.value, width),
+                Some(width),
+            )),
+            Some(width) => Some(ConstValue::new(value.value & mask(width), Some(width))),
+            None => Some(value),
+        }
+    }
+}
+
+impl ConstEnv for ScopeEnv<'_> {
+    fn value(&self, package: Option<&str>, ident: &str) -> Option<ConstValue> {
+        if package.is_none() {
+            if let Some(value) = self.overrides.get(ident) {
+                return Some(*value);
+            }
+        }
+        let def = self.find(package, ident)?;
+        if !has_value(def) {
+            return None;
+        }
+        self.param_value(def)
+    }
+
+    fn width(&self, package: Option<&str>, ident: &str) -> Option<u32> {
+        let def = self.find(package, ident)?;
+        let type_str = def.type_str();
+        let env = self.at(def)?;
+        match param_expr(&type_str) {
+            // parameters without an explicit type take the width of their value
+            Some((decl, _)) => type_width(&clean_type_str(decl, ident), &env)
+                .or_else(|| self.param_value(def)?.width),
+            None => type_width(&type_str, &env),
+        }
+    }
+}
+
+/// whether a definition is a constant with a value expression, a parameter or an enum literal
+fn has_value(def: &dyn Definition) -> bool {
+    def.completion_kind() == CompletionItemKind::TYPE_PARAMETER
+        || def.completion_kind() == CompletionItemKind::ENUM_MEMBER
+}
+
+/// parameters of a module that can be overridden by an instantiation, in declaration order
+pub fn module_params(module: &dyn Scope) -> Vec<&dyn Definition> {
+    module
+        .defs()
+        .iter()
+        .filter(|x| {
+            x.completion_kind() == CompletionItemKind::TYPE_PARAMETER
+                && !x.type_str().trim_start().starts_with("localparam")
+        })
+        .map(|x| x.as_ref())
+        .collect()
+}
+
+/// pair the parameter overrides of a module instantiation with the identifiers of the
+/// instantiated module's parameters, ordered overrides are matched by position
+pub fn named_overrides(
+    inst: &dyn Definition,
+    module: Option<&dyn Scope>,
+) -> Vec<(Option<String>, String)> {
+    let params = match module {
+        Some(module) => module_params(module),
+        None => Vec::new(),
+    };
+    inst.param_overrides()
+        .into_iter()
+        .enumerate()
+        .map(|(i, (ident, expr))| (ident.or_else(|| Some(params.get(i)?.ident())), expr))
+        .collect()
+}
+
+/// evaluate the parameter overrides of a module instantiation, the overrides are evaluated at
+/// the instantiation and keyed by the identifiers of the instantiated module's parameters
+pub fn instance_overrides(
+    scope_tree: &GenericScope,
+    inst: &dyn Definition,
+    module: &dyn Scope,
+) -> HashMap<String, ConstValue> {
+    let mut overrides = HashMap::new();
+    let no_overrides = HashMap::new();
+    let env = ScopeEnv::new(scope_tree, &inst.url(), inst.byte_idx(), &no_overrides);
+    for (ident, expr) in named_overrides(inst, Some(module)) {
+        if let (Some(ident), Some(value)) = (ident, eval_const_expr(&expr, &env)) {
+            overrides.insert(ident, value);
+        }
+    }
+    overrides
+}
+
+/// computed values to show alongside the hover text of a parameter, enum literal or module
+/// instantiation
+pub fn value_hover(scope_tree: &GenericScope, def: &dyn Definition) -> String {
+    let no_overrides = HashMap::new();
+    if has_value(def) {
+        let env = ScopeEnv::new(scope_tree, &def.url(), def.byte_idx(), &no_overrides);
+        return match env.param_value(def) {
+            Some(value) => format!(" // = {}", value.display()),
+            None => String::new(),
+        };
+    }
+    let module = match def.mod_ident() {
+        Some(mod_ident) => scope_tree.scopes().iter().find(|x| x.ident() == mod_ident),
+        None => None,
+    };
+    let mut hover = String::new();
+    if let Some(module) = module {
+        let overrides = instance_overrides(scope_tree, def, module.as_ref());
+        let env = ScopeEnv::new(scope_tree, &module.url(), module.byte_idx(), &overrides);
+        for param in module_params(module.as_ref()) {
+            if let Some(value) = env.param_value(param) {
+                hover.push_str(&format!("\n// {} = {}", param.ident(), value.display()));
+            }
+        }
+    }
+    hover
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::get_scopes;
+    use crate::sources::{parse, LSPSupport};
+    use crate::support::test_init;
+    use ropey::Rope;
+
+    struct MapEnv(HashMap<String, ConstValue>);
+
+    impl ConstEnv for MapEnv {
+        fn value(&self, _: Option<&str>, ident: &str) -> Option<ConstValue> {
+            self.0.get(ident).copied()
+        }
+        fn width(&self, _: Option<&str>, ident: &str) -> Option<u32> {
+            self.0.get(ident)?.width
+        }
+    }
+
+    fn eval(expr: &str) -> Option<i128> {
+        let mut map = HashMap::new();
+        map.insert("WIDTH".to_string(), ConstValue::new(16, Some(32)));
+        map.insert("NIBBLE".to_string(), ConstValue::new(0xA, Some(4)));
+        Some(eval_const_expr(expr, &MapEnv(map))?.value)
+    }
+
+    #[test]
+    fn test_eval_arithmetic() {
+        test_init();
+        assert_eq!(eval("1 + 2 * 3"), Some(7));
+        assert_eq!(eval("(1 + 2) * 3"), Some(9));
+        assert_eq!(eval("2 ** 3 ** 2"), Some(512));
+        assert_eq!(eval("WIDTH / 4 - 1"), Some(3));
+        assert_eq!(eval("1 << 4 | 1"), Some(17));
+        assert_eq!(eval("WIDTH > 8 ? 1 : 0"), Some(1));
+        assert_eq!(eval("-WIDTH"), Some(-16));
+        assert_eq!(eval("8 'h FF"), Some(255));
+        assert_eq!(eval("'b1010_1010"), Some(170));
+        assert_eq!(eval("WIDTH[4]"), Some(1));
+        assert_eq!(eval("UNKNOWN + 1"), None);
+        assert_eq!(eval("1 +"), None);
+    }
+
+    #[test]
+    fn test_eval_system_functions() {
+        test_init();
+        assert_eq!(eval("$clog2(WIDTH)"), Some(4));
+        assert_eq!(eval("$clog2(17)"), Some(5));
+        assert_eq!(eval("$clog2(1)"), Some(0));
+        assert_eq!(eval("$bits(logic [7:0][3:0])"), Some(32));
+        assert_eq!(eval("$bits(NIBBLE)"), Some(4));
+        // ranges too large for a width
+        assert_eq!(eval("$bits(logic [4294967295:0])"), None);
+        assert_eq!(eval("$bits(logic [65536:0][65536:0])"), None);
+        assert_eq!(
+            eval("WIDTH[170141183460469231731687303715884105727:-1]"),
+            None
+        );
+        assert_eq!(eval("$bits({NIBBLE, 4'h0})"), Some(8));
+    }
+
+    #[test]
+    fn test_eval_concatenation() {
+        test_init();
+        assert_eq!(eval("{4'hA, 4'h5}"), Some(0xA5));
+        assert_eq!(eval("{NIBBLE, 1'b1}"), Some(0x15));
+        assert_eq!(eval("{4{2'b10}}"), Some(0xAA));
+        assert_eq!(eval("{WIDTH/8{NIBBLE}}"), Some(0xAA));
+        // unsized values can't be concatenated
+        assert_eq!(eval("{1, 2}"), None);
+        // too wide to hold without losing bits
+        assert_eq!(eval("{20{8'hFF}}"), None);
+        assert_eq!(eval("{4294967295{1'b1}}"), None);
+        assert_eq!(eval("{16{8'hFF}, 1'b1}"), None);
+    }
+
+    #[test]
+    fn test_param_values() {
+        test_init();
+        let text = r#"package cfg_pkg;
+  parameter int DEPTH = 16;
+endpackage
+module test #(parameter WIDTH = 8) ();
+  import cfg_pkg::*;
+  localparam logic [3:0] NARROW = 8'hFF;
+  localparam ADDR_W = $clog2(cfg_pkg::DEPTH) + 1;
+  localparam TOTAL = WIDTH * DEPTH;
+  localparam BITS = $bits(NARROW);
+  localparam int NEG = -1;
+  localparam byte B = -2;
+  localparam logic signed [3:0] S = 4'hF;
+  localparam int unsigned U = -1;
+endmodule
+"#;
+        let doc = Rope::from_str(text);
+        let url = Url::parse("file:///test.sv").unwrap();
+        let syntax_tree = parse(&doc, &url, &None, &Vec::new()).unwrap();
+        let scope_tree = get_scopes(&syntax_tree, &url).unwrap();
+        let overrides = HashMap::new();
+        let env = ScopeEnv::new(&scope_tree, &url, 0, &overrides);
+        let value = |ident: &str| {
+            let byte_idx = text.find("endmodule").unwrap();
+            let def = scope_tree.find_definition(ident, byte_idx, &url).unwrap();
+            env.param_value(def).map(|x| x.value)
+        };
+        assert_eq!(value("NARROW"), Some(15));
+        assert_eq!(value("ADDR_W"), Some(5));
+        assert_eq!(value("TOTAL"), Some(128));
+        assert_eq!(value("BITS"), Some(4));
+        assert_eq!(value("NEG"), Some(-1));
+        assert_eq!(value("B"), Some(-2));
+        assert_eq!(value("S"), Some(-1));
+        assert_eq!(value("U"), Some(4294967295));
+        let hover = |ident: &str| {
+            let byte_idx = text.find("endmodule").unwrap();
+            let def = scope_tree.find_definition(ident, byte_idx, &url).unwrap();
+            value_hover(&scope_tree, def)
+        };
+        assert_eq!(hover("NEG"), " // = -1 (0xFFFFFFFF)");
+        assert_eq!(hover("B"), " // = -2 (0xFE)");
+        assert_eq!(hover("S"), " // = -1 (0xF)");
+        assert_eq!(hover("U"), " // = 4294967295 (0xFFFFFFFF)");
+
+        let mut overrides = HashMap::new();
+        overrides.insert("WIDTH".to_string(), ConstValue::new(2, None));
+        let env = ScopeEnv::new(&scope_tree, &url, 0, &overrides);
+        let byte_idx = text.find("endmodule").unwrap();
+        let def = scope_tree.find_definition("TOTAL", byte_idx, &url).unwrap();
+        assert_eq!(env.param_value(def).map(|x| x.value), Some(32));
+    }
+
+    #[test]
+    fn test_enum_values() {
+        test_init();
+        let text = r#"package state_pkg;
+  typedef enum logic [2:0] {IDLE, BUSY = 3, S[2], T[5:4]} state_t;
+endpackage
+module test;
+  enum {RED, GREEN} color;
+  typedef enum {P[4'd2], Q, R['h2:'h3]} p_t;
+  typedef enum {U[4'hx], V, W[100000]} u_t;
+  logic after;
+endmodule
+"#;
+        let doc = Rope::from_str(text);
+        let url = Url::parse("file:///test.sv").unwrap();
+        let syntax_tree = parse(&doc, &url, &None, &Vec::new()).unwrap();
+        let scope_tree = get_scopes(&syntax_tree, &url).unwrap();
+        let byte_idx = text.find("endpackage").unwrap();
+        let hover = |ident: &str| {
+            let def = scope_tree.find_definition(ident, byte_idx, &url).unwrap();
+            value_hover(&scope_tree, def)
+        };
+        assert_eq!(hover("IDLE"), " // = 0 (0x0)");
+        assert_eq!(hover("S0"), " // = 4 (0x4)");
+        assert_eq!(hover("S1"), " // = 5 (0x5)");
+        assert_eq!(hover("T5"), " // = 6 (0x6)");
+        assert_eq!(hover("T4"), " // = 7 (0x7)");
+        let hover_at = |ident: &str, byte_idx: usize| {
+            let def = scope_tree.find_definition(ident, byte_idx, &url).unwrap();
+            value_hover(&scope_tree, def)
+        };
+        let byte_idx = text.find("endmodule").unwrap();
+        let def = scope_tree.find_definition("GREEN", byte_idx, &url).unwrap();
+        assert_eq!(value_hover(&scope_tree, def), " // = 1 (0x1)");
+        // ranges bounded by based numbers
+        assert_eq!(hover_at("P1", byte_idx), " // = 1 (0x1)");
+        assert_eq!(hover_at("Q", byte_idx), " // = 2 (0x2)");
+        assert_eq!(hover_at("R3", byte_idx), " // = 4 (0x4)");
+        // ranges which can't be expanded keep their base name
+        assert_eq!(hover_at("U", byte_idx), " // = 0 (0x0)");
+        assert_eq!(hover_at("V", byte_idx), " // = 1 (0x1)");
+        assert!(scope_tree.find_definition("W", byte_idx, &url).is_some());
+        assert!(scope_tree.find_definition("u_t", byte_idx, &url).is_some());
+        assert!(scope_tree
+            .find_definition("after", byte_idx, &url)
+            .is_some());
+        let byte_idx = text.find("endpackage").unwrap();
+        let def = scope_tree.find_definition("BUSY", byte_idx, &url).unwrap();
+        assert_eq!(doc.byte_to_pos(def.byte_idx()), Position::new(1, 34));
+        assert_eq!(def.completion_kind(), CompletionItemKind::ENUM_MEMBER);
+    }
+}
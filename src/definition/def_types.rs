@@ -1,8 +1,15 @@
+use crate::completion::keyword::KEYWORDS;
 use crate::sources::LSPSupport;
 use log::trace;
 use ropey::Rope;
+use serde::{Deserialize, Serialize};
 use tower_lsp::lsp_types::*;
 
+// limit on following typedefs of typedefs when resolving a type
+const MAX_TYPEDEF_DEPTH: usize = 16;
+// limit on following base classes when resolving class members
+const MAX_INHERITANCE_DEPTH: usize = 16;
+
 /// cleanup the text of a definition so it can be included in completions
 pub fn clean_type_str(type_str: &str, ident: &str) -> String {
     let endings: &[_] = &[';', ','];
@@ -23,6 +30,143 @@ pub fn clean_type_str(type_str: &str, ident: &str) -> String {
         .replace(" : ", ":")
 }
 
+/// whether a class member is static, the qualifiers come before the function or task keyword
+pub fn is_static(def: &dyn Definition) -> bool {
+    def.type_str()
+        .split_whitespace()
+        .take_while(|x| !matches!(*x, "function" | "task"))
+        .any(|x| x == "static")
+}
+
+/// whether a class member can be accessed through the class scope, ex. `cls::member`: static
+/// properties and methods, parameters, types and enum literals
+fn is_class_scoped(def: &dyn Definition) -> bool {
+    match def.def_type() {
+        DefinitionType::Subroutine => is_static(def),
+        DefinitionType::GenericScope | DefinitionType::Class => true,
+        _ => {
+            is_static(def)
+                || def.completion_kind() == CompletionItemKind::TYPE_PARAMETER
+                || def.completion_kind() == CompletionItemKind::ENUM_MEMBER
+                || def.type_str().split_whitespace().next() == Some("typedef")
+        }
+    }
+}
+
+/// the data of a completion item, which locates its definition so the item can be resolved
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompletionData {
+    pub uri: Url,
+    pub byte_idx: usize,
+}
+
+pub fn completion_data(url: &Url, byte_idx: usize) -> Option<serde_json::Value> {
+    serde_json::to_value(CompletionData {
+        uri: url.clone(),
+        byte_idx,
+    })
+    .ok()
+}
+
+/// whether a completion token matches an identifier, either as a prefix or fuzzily, where each
+/// character of the token continues the current underscore or camel case segment of the
+/// identifier or starts a later one, ex. `rdp` matches `rd_ptr_q` and `wr_e` matches `wrEn`
+pub fn fuzzy_match(ident: &str, token: &str) -> bool {
+    fn segment_start(ident: &[char], idx: usize) -> bool {
+        idx == 0
+            || (ident[idx] != '_' && ident[idx - 1] == '_')
+            || (ident[idx].is_uppercase() && ident[idx - 1].is_lowercase())
+            || (ident[idx].is_ascii_digit() && !ident[idx - 1].is_ascii_digit())
+    }
+    // matched[i][j] is whether token[j..] matches ident[i..], with the first character either
+    // continuing the segment at i or starting a later one, later[i][j] only allows the latter
+    fn matches(ident: &[char], token: &[char]) -> bool {
+        let eq = |x: char, y: char| x.to_lowercase().eq(y.to_lowercase());
+        let mut matched = vec![vec![false; token.len() + 1]; ident.len() + 2];
+        let mut later = vec![vec![false; token.len() + 1]; ident.len() + 2];
+        for i in (0..=ident.len()).rev() {
+            matched[i][token.len()] = true;
+            later[i][token.len()] = true;
+            for j in (0..token.len()).rev() {
+                let here = i < ident.len() && eq(ident[i], token[j]) && matched[i + 1][j + 1];
+                later[i][j] = later[i + 1][j] || (here && segment_start(ident, i));
+                matched[i][j] = (i > 0 && here) || later[i][j];
+            }
+        }
+        matched[0][0]
+    }
+    if ident.starts_with(token) {
+        return true;
+    }
+    let ident: Vec<char> = ident.chars().collect();
+    // underscores in the token only separate segments
+    let token: Vec<char> = token.chars().filter(|x| *x != '_').collect();
+    matches(&ident, &token)
+}
+
+/// set the sort text of a completion, ranking it by how close to the user it's defined, then
+/// by whether the token is a prefix of it rather than a fuzzy match
+pub fn rank_completion(item: &mut CompletionItem, rank: usize, token: &str) {
+    let fuzzy = !item.label.starts_with(token) as usize;
+    item.sort_text = Some(format!("{}{}{}", rank, fuzzy, item.label));
+    if item.filter_text.is_none() {
+        item.filter_text = Some(item.label.clone());
+    }
+}
+
+/// whether a scope is a module, interface or program
+pub fn is_design_unit(scope: &dyn Scope) -> bool {
+    let keyword = scope.type_str();
+    let keyword = keyword.split_whitespace().next().unwrap_or_default();
+    matches!(keyword, "module" | "macromodule" | "interface" | "program")
+}
+
+/// the user defined type named in the text of a definition, as (package, type identifier),
+/// ex. `input my_pkg::req_t [1:0] req` gives (Some(my_pkg), req_t)
+pub fn type_name(type_str: &str, ident: &str) -> Option<(Option<String>, String)> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut chars = type_str.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_alphanumeric() || c == '_' || c == '$' {
+            let mut token = c.to_string();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_' || c == '$') {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        } else if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            tokens.push("::".to_string());
+        } else if !c.is_whitespace() {
+            tokens.push(c.to_string());
+        }
+    }
+    let mut depth = 0;
+    let mut package: Option<String> = None;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.as_str() {
+            "[" | "(" | "{" => depth += 1,
+            "]" | ")" | "}" => depth -= 1,
+            "=" if depth == 0 => break,
+            _ if depth != 0 => (),
+            _ if tokens.get(i + 1).map(|x| x.as_str()) == Some("::") => {
+                package = Some(token.clone())
+            }
+            _ if token.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                && token != ident
+                && !KEYWORDS.iter().any(|x| x.0 == token) =>
+            {
+                return Some((package, token.clone()));
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
 pub fn copy_defs(defs: &[Box<dyn Definition>]) -> Vec<Box<dyn Definition>> {
     let mut decs: Vec<Box<dyn Definition>> = Vec::new();
     for def in defs {
@@ -84,6 +228,27 @@ pub trait Definition: std::fmt::Debug + Sync + Send {
     // constructs the completion for this definition
     fn completion(&self) -> CompletionItem;
     fn dot_completion(&self, scope_tree: &GenericScope) -> Vec<CompletionItem>;
+    // parameter overrides of a module instantiation, as (parameter, expression) pairs where
+    // the parameter is None for ordered assignments
+    fn param_overrides(&self) -> Vec<(Option<String>, String)> {
+        Vec::new()
+    }
+    // the module, interface or program a module instantiation instantiates
+    fn mod_ident(&self) -> Option<String> {
+        None
+    }
+    // this definition as a scope, if it is one
+    fn as_scope(&self) -> Option<&dyn Scope> {
+        None
+    }
+    // the class of an out-of-block method body, ex. cls in `function void cls::m();`
+    fn class_scope(&self) -> Option<String> {
+        None
+    }
+    // whether this is a package import making the given identifier visible, ex. `import pkg::*`
+    fn imports(&self, _ident: &str) -> bool {
+        false
+    }
 }
 
 pub trait Scope: std::fmt::Debug + Definition + Sync + Send {
@@ -95,6 +260,16 @@ pub trait Scope: std::fmt::Debug + Definition + Sync + Send {
     fn defs(&self) -> &Vec<Box<dyn Definition>>;
     // all the scopes within this scope, ex. task inside a module
     fn scopes(&self) -> &Vec<Box<dyn Scope>>;
+    fn defs_mut(&mut self) -> &mut Vec<Box<dyn Definition>>;
+    fn scopes_mut(&mut self) -> &mut Vec<Box<dyn Scope>>;
+    // the class this class extends, as (package, class)
+    fn extends(&self) -> Option<(Option<String>, String)> {
+        None
+    }
+    // the interface classes this class implements, or for an interface class, extends
+    fn implements(&self) -> Vec<(Option<String>, String)> {
+        Vec::new()
+    }
     // the definition of this scope
     fn definition(&self) -> GenericDec {
         GenericDec {
@@ -108,13 +283,21 @@ pub trait Scope: std::fmt::Debug + Definition + Sync + Send {
         }
     }
     /// return a completion from the scope tree, this function should be called on the global scope
-    fn get_completion(&self, token: &str, byte_idx: usize, url: &Url) -> Vec<CompletionItem> {
+    /// depth is how deep this scope is in the scope tree, which ranks its completions: local
+    /// definitions rank above those of the module, which rank above packages and global ones
+    fn get_completion(
+        &self,
+        token: &str,
+        byte_idx: usize,
+        url: &Url,
+        depth: usize,
+    ) -> Vec<CompletionItem> {
         let mut completions: Vec<CompletionItem> = Vec::new();
         // first we need to go down the scope tree, to the scope the user is invoking a completion
         // in
         for scope in self.scopes() {
             if &scope.url() == url && scope.start() <= byte_idx && byte_idx <= scope.end() {
-                completions = scope.get_completion(token, byte_idx, url);
+                completions = scope.get_completion(token, byte_idx, url, depth + 1);
                 break;
             }
         }
@@ -122,14 +305,23 @@ pub trait Scope: std::fmt::Debug + Definition + Sync + Send {
         // we proceed back upwards through the scope tree, adding any definitions that match
         // the users token
         let completion_idents: Vec<String> = completions.iter().map(|x| x.label.clone()).collect();
+        let rank = match depth {
+            0 => 3,
+            1 => 2,
+            _ => 1,
+        };
         for def in self.defs() {
-            if !completion_idents.contains(&def.ident()) && def.starts_with(token) {
-                completions.push(def.completion());
+            if !completion_idents.contains(&def.ident()) && fuzzy_match(&def.ident(), token) {
+                let mut item = def.completion();
+                rank_completion(&mut item, rank, token);
+                completions.push(item);
             }
         }
         for scope in self.scopes() {
-            if scope.starts_with(token) {
-                completions.push(scope.completion());
+            if fuzzy_match(&scope.ident(), token) {
+                let mut item = scope.completion();
+                rank_completion(&mut item, rank, token);
+                completions.push(item);
             }
         }
         completions
@@ -215,6 +407,628 @@ pub trait Scope: std::fmt::Debug + Definition + Sync + Send {
         }
         definition
     }
+
+    /// return a reference to a definition in the scope tree, this function should be called on
+    /// the global scope
+    fn find_definition(&self, token: &str, byte_idx: usize, url: &Url) -> Option<&dyn Definition> {
+        for scope in self.scopes() {
+            if &scope.url() == url && scope.start() <= byte_idx && byte_idx <= scope.end() {
+                if let Some(def) = scope.find_definition(token, byte_idx, url) {
+                    return Some(def);
+                }
+                break;
+            }
+        }
+        for def in self.defs() {
+            // macros have their own namespace
+            if def.ident() == token
+                && def.def_type() != DefinitionType::Macro
+                && def.def_type() != DefinitionType::Declaration
+            {
+                return Some(def.as_ref());
+            }
+        }
+        for scope in self.scopes() {
+            if scope.ident() == token {
+                return Some(scope.as_ref());
+            }
+        }
+        // only a declaration, the definition may not be indexed
+        self.defs()
+            .iter()
+            .find(|x| x.ident() == token && x.def_type() == DefinitionType::Declaration)
+            .map(|x| x.as_ref())
+    }
+
+    /// find the `define of a macro, preferring one in the file with the given url. Macros are
+    /// global, so this function should be called on the global scope
+    fn find_macro(&self, ident: &str, url: &Url) -> Option<&dyn Definition> {
+        let macros = self.find_all(ident, DefinitionType::Macro);
+        macros
+            .iter()
+            .find(|x| &x.url() == url)
+            .or_else(|| macros.first())
+            .copied()
+    }
+
+    /// all the definitions of the given type and identifier in this scope and its child scopes
+    fn find_all(&self, ident: &str, def_type: DefinitionType) -> Vec<&dyn Definition> {
+        let mut defs: Vec<&dyn Definition> = self
+            .defs()
+            .iter()
+            .filter(|x| x.def_type() == def_type && x.ident() == ident)
+            .map(|x| x.as_ref())
+            .collect();
+        for scope in self.scopes() {
+            defs.extend(scope.find_all(ident, def_type));
+        }
+        defs
+    }
+
+    /// resolve a hierarchical name such as `g_lane[0].sig` given as a path of identifiers, with
+    /// any selects removed. A "::" element follows a package or class scope, ex. [pkg, ::, W].
+    /// This function should be called on the global scope
+    fn find_path(&self, path: &[String], byte_idx: usize, url: &Url) -> Option<&dyn Definition> {
+        let (first, rest) = path.split_first()?;
+        if let Some(ident) = first.strip_prefix('`') {
+            return self.find_macro(ident, url);
+        }
+        let package = self
+            .scopes()
+            .iter()
+            .find(|x| &x.ident() == first && x.symbol_kind() == SymbolKind::PACKAGE);
+        let mut def = match package {
+            // packages have their own namespace
+            Some(package) if rest.first().map(|x| x.as_str()) == Some("::") => {
+                package.as_ref() as &dyn Definition
+            }
+            _ => match self.find_definition(first, byte_idx, url) {
+                Some(def) => def,
+                // a member inherited by the class the name is used in
+                None => self.inherited_child(self.enclosing_class(byte_idx, url)?, first)?,
+            },
+        };
+        let rest: Vec<&String> = rest.iter().filter(|x| *x != "::").collect();
+        for (i, ident) in rest.iter().enumerate() {
+            let member = self.find_member(def, ident)?;
+            // the signals of a modport are those of its interface, ex. intf.mp.sig
+            if member.def_type() == DefinitionType::Modport && i + 1 < rest.len() {
+                continue;
+            }
+            def = member;
+        }
+        Some(def)
+    }
+
+    /// find a member of a definition, ex. a signal within a generate block, an instantiated
+    /// module, a struct or a class including its base classes. This function should be called
+    /// on the global scope
+    fn find_member<'a>(
+        &'a self,
+        def: &'a dyn Definition,
+        ident: &str,
+    ) -> Option<&'a dyn Definition> {
+        self.inherited_child(self.member_scope(def)?, ident)
+    }
+
+    /// the scope holding the members of a definition: the definition itself if it is a scope,
+    /// the module of an instantiation, or the struct, union or interface its type resolves to.
+    /// This function should be called on the global scope
+    fn member_scope<'a>(&'a self, def: &'a dyn Definition) -> Option<&'a dyn Scope> {
+        if let Some(scope) = def.as_scope() {
+            return Some(scope);
+        }
+        let mut def = def;
+        // follow typedef aliases, with a limit in case of circular typedefs
+        for _ in 0..MAX_TYPEDEF_DEPTH {
+            def = self.find_type(def)?;
+            if let Some(scope) = def.as_scope() {
+                return Some(scope);
+            }
+        }
+        None
+    }
+
+    /// the definition of the type of a definition: the module of an instantiation, or the
+    /// typedef, class or interface named in its declaration. This function should be called on
+    /// the global scope
+    fn find_type(&self, def: &dyn Definition) -> Option<&dyn Definition> {
+        if let Some(mod_ident) = def.mod_ident() {
+            return self
+                .scopes()
+                .iter()
+                .find(|x| x.ident() == mod_ident)
+                .map(|x| x.as_ref() as &dyn Definition);
+        }
+        let (package, type_ident) = type_name(&def.type_str(), &def.ident())?;
+        self.find_qualified(package.as_deref(), &type_ident, def.byte_idx(), &def.url())
+    }
+
+    /// all classes within this scope, including those nested in packages and modules
+    fn classes(&self) -> Vec<&dyn Scope> {
+        let mut classes: Vec<&dyn Scope> = Vec::new();
+        for scope in self.scopes() {
+            if scope.def_type() == DefinitionType::Class {
+                classes.push(scope.as_ref());
+            }
+            classes.append(&mut scope.classes());
+        }
+        classes
+    }
+
+    /// the scope whose identifier is at the given location
+    fn scope_at(&self, url: &Url, byte_idx: usize) -> Option<&dyn Scope> {
+        for scope in self.scopes() {
+            if &scope.url() == url && scope.byte_idx() == byte_idx {
+                return Some(scope.as_ref());
+            }
+            if let Some(found) = scope.scope_at(url, byte_idx) {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    /// the definition whose identifier is at the given location, with the scopes containing it
+    /// from the outermost
+    fn locate(&self, url: &Url, byte_idx: usize) -> Option<(&dyn Definition, Vec<&dyn Scope>)> {
+        let is_at = |x: &dyn Definition| &x.url() == url && x.byte_idx() == byte_idx;
+        if let Some(def) = self.defs().iter().find(|x| is_at(x.as_ref())) {
+            return Some((def.as_ref(), Vec::new()));
+        }
+        for scope in self.scopes() {
+            if is_at(scope.as_ref()) {
+                return Some((scope.as_ref(), Vec::new()));
+            }
+            if &scope.url() == url && scope.start() <= byte_idx && byte_idx <= scope.end() {
+                if let Some((def, mut scopes)) = scope.locate(url, byte_idx) {
+                    scopes.insert(0, scope.as_ref());
+                    return Some((def, scopes));
+                }
+            }
+        }
+        None
+    }
+
+    /// the innermost class containing the given location
+    fn enclosing_class(&self, byte_idx: usize, url: &Url) -> Option<&dyn Scope> {
+        self.classes()
+            .into_iter()
+            .filter(|x| &x.url() == url && x.start() <= byte_idx && byte_idx <= x.end())
+            .max_by_key(|x| x.start())
+    }
+
+    /// classes that directly extend or implement the given class, resolving the names of their
+    /// base classes so that classes of the same name in other packages are told apart. This
+    /// function should be called on the global scope
+    fn subclasses(&self, class: &dyn Scope) -> Vec<&dyn Scope> {
+        let is_class = |x: &dyn Scope| x.byte_idx() == class.byte_idx() && x.url() == class.url();
+        self.classes()
+            .into_iter()
+            .filter(|x| {
+                x.extends()
+                    .into_iter()
+                    .chain(x.implements())
+                    .any(|x| x.1 == class.ident())
+                    && self.superclasses(*x).into_iter().any(is_class)
+            })
+            .collect()
+    }
+
+    /// classes that extend or implement the given class, directly or through other classes.
+    /// This function should be called on the global scope
+    fn derived_classes(&self, class: &dyn Scope) -> Vec<&dyn Scope> {
+        let same =
+            |x: &dyn Scope, y: &dyn Scope| x.byte_idx() == y.byte_idx() && x.url() == y.url();
+        let mut derived: Vec<&dyn Scope> = Vec::new();
+        let mut i = 0;
+        // with each class visited once in case of circular inheritance
+        let mut base = Some(class);
+        while let Some(next) = base {
+            for scope in self.subclasses(next) {
+                if !same(scope, class) && !derived.iter().any(|x| same(*x, scope)) {
+                    derived.push(scope);
+                }
+            }
+            base = derived.get(i).copied();
+            i += 1;
+        }
+        derived
+    }
+
+    /// the classes a class directly extends or implements. This function should be called on
+    /// the global scope
+    fn superclasses(&self, class: &dyn Scope) -> Vec<&dyn Scope> {
+        class
+            .extends()
+            .into_iter()
+            .chain(class.implements())
+            .filter_map(|(package, ident)| {
+                self.find_qualified(package.as_deref(), &ident, class.byte_idx(), &class.url())?
+                    .as_scope()
+            })
+            .filter(|x| x.def_type() == DefinitionType::Class)
+            .collect()
+    }
+
+    /// the class a class extends. This function should be called on the global scope
+    fn base_class(&self, class: &dyn Scope) -> Option<&dyn Scope> {
+        let (package, ident) = class.extends()?;
+        self.find_qualified(package.as_deref(), &ident, class.byte_idx(), &class.url())?
+            .as_scope()
+    }
+
+    /// a member of a scope, or for a class, a member inherited from its base classes. This
+    /// function should be called on the global scope
+    fn inherited_child<'a>(
+        &'a self,
+        scope: &'a dyn Scope,
+        ident: &str,
+    ) -> Option<&'a dyn Definition> {
+        let mut scope = scope;
+        // with a limit in case of circular inheritance
+        for _ in 0..MAX_INHERITANCE_DEPTH {
+            if let Some(def) = scope.child(ident) {
+                return Some(def);
+            }
+            scope = self.base_class(scope)?;
+        }
+        None
+    }
+
+    /// out-of-block bodies of a method of the given class, ex. `function void cls::m();`
+    fn method_bodies(&self, class: &str, ident: &str) -> Vec<&dyn Definition> {
+        let mut bodies: Vec<&dyn Definition> = Vec::new();
+        for scope in self.scopes() {
+            if scope.ident() == ident && scope.class_scope().as_deref() == Some(class) {
+                bodies.push(scope.as_ref());
+            }
+            bodies.append(&mut scope.method_bodies(class, ident));
+        }
+        bodies
+    }
+
+    /// the class of an out-of-block method body, resolved from the body's location. This
+    /// function should be called on the global scope
+    fn body_class(&self, body: &dyn Definition, class: &str) -> Option<&dyn Scope> {
+        self.find_qualified(None, class, body.byte_idx(), &body.url())?
+            .as_scope()
+            .filter(|x| x.def_type() == DefinitionType::Class)
+    }
+
+    /// the implementations of a definition: the out-of-block bodies and overrides of a class
+    /// method, or the classes extending or implementing a class. This function should be
+    /// called on the global scope
+    fn find_implementations(&self, def: &dyn Definition) -> Vec<&dyn Definition> {
+        if def.def_type() == DefinitionType::Class {
+            return match def.as_scope() {
+                Some(class) => self
+                    .derived_classes(class)
+                    .into_iter()
+                    .map(|x| x as &dyn Definition)
+                    .collect(),
+                None => Vec::new(),
+            };
+        }
+        if def.def_type() != DefinitionType::Subroutine {
+            return Vec::new();
+        }
+        let is_def = |x: &dyn Definition| x.byte_idx() == def.byte_idx() && x.url() == def.url();
+        // the class the method belongs to, the innermost class containing a prototype
+        let class = match def.class_scope() {
+            Some(class) => self.body_class(def, &class),
+            None => self.enclosing_class(def.byte_idx(), &def.url()),
+        };
+        let class = match class {
+            Some(class) => class,
+            None => return Vec::new(),
+        };
+        let same =
+            |x: &dyn Scope, y: &dyn Scope| x.byte_idx() == y.byte_idx() && x.url() == y.url();
+        let mut classes = vec![class];
+        classes.extend(self.derived_classes(class));
+        let mut implementations: Vec<&dyn Definition> = Vec::new();
+        for class in classes {
+            if let Some(method) = class.child(&def.ident()) {
+                if method.def_type() == DefinitionType::Subroutine && !is_def(method) {
+                    implementations.push(method);
+                }
+            }
+            for body in self.method_bodies(&class.ident(), &def.ident()) {
+                let of_class = body
+                    .class_scope()
+                    .and_then(|x| self.body_class(body, &x))
+                    .is_some_and(|x| same(x, class));
+                if of_class && !is_def(body) {
+                    implementations.push(body);
+                }
+            }
+        }
+        implementations
+    }
+
+    /// the declarations of a definition: the prototype of an extern method, and the forward
+    /// typedefs of a class or other type. This function should be called on the global scope
+    fn find_declarations<'a>(&'a self, def: &'a dyn Definition) -> Vec<&'a dyn Definition> {
+        match def.def_type() {
+            DefinitionType::Subroutine => {
+                let prototype = def
+                    .class_scope()
+                    .and_then(|class| self.classes().into_iter().find(|x| x.ident() == class))
+                    .and_then(|class| class.child(&def.ident()))
+                    .filter(|x| x.def_type() == DefinitionType::Subroutine);
+                vec![prototype.unwrap_or(def)]
+            }
+            DefinitionType::Declaration => vec![def],
+            _ => {
+                let forward: Vec<&dyn Definition> = self
+                    .find_all(&def.ident(), DefinitionType::Declaration)
+                    .into_iter()
+                    .filter(|x| x.symbol_kind() != SymbolKind::FUNCTION)
+                    .collect();
+                match forward.is_empty() {
+                    true => vec![def],
+                    false => forward,
+                }
+            }
+        }
+    }
+
+    /// all the definitions of a name resolved to def, for names defined more than once such as
+    /// modules with the same name in different libraries. This function should be called on the
+    /// global scope
+    fn find_candidates<'a>(&'a self, def: &'a dyn Definition) -> Vec<&'a dyn Definition> {
+        if def.def_type() == DefinitionType::Macro {
+            return self.find_all(&def.ident(), DefinitionType::Macro);
+        }
+        // the bodies of an extern method, defined outside of its class
+        if def.def_type() == DefinitionType::Subroutine && def.class_scope().is_none() {
+            if let Some(class) = self.enclosing_class(def.byte_idx(), &def.url()) {
+                let bodies = self.method_bodies(&class.ident(), &def.ident());
+                if !bodies.is_empty() {
+                    return bodies;
+                }
+            }
+        }
+        let is_def = |x: &dyn Definition| x.byte_idx() == def.byte_idx() && x.url() == def.url();
+        let same_name = |x: &dyn Definition| {
+            x.ident() == def.ident()
+                && x.def_type() == def.def_type()
+                && x.symbol_kind() == def.symbol_kind()
+        };
+        let global_defs = self.defs().iter().map(|x| x.as_ref());
+        let global_scopes = self.scopes().iter().map(|x| x.as_ref() as &dyn Definition);
+        let globals: Vec<&dyn Definition> = global_defs.chain(global_scopes).collect();
+        // names declared within a scope are unique
+        if !globals.iter().any(|x| is_def(*x)) {
+            return vec![def];
+        }
+        globals.into_iter().filter(|x| same_name(*x)).collect()
+    }
+
+    /// find a definition, optionally within a package. Without a package, the identifier is
+    /// looked up from the given location, falling back to package members which may have been
+    /// imported. This function should be called on the global scope
+    fn find_qualified(
+        &self,
+        package: Option<&str>,
+        ident: &str,
+        byte_idx: usize,
+        url: &Url,
+    ) -> Option<&dyn Definition> {
+        match package {
+            Some(package) => self
+                .scopes()
+                .iter()
+                .find(|x| x.ident() == package)?
+                .child(ident),
+            None => self.find_definition(ident, byte_idx, url).or_else(|| {
+                self.package_imports(byte_idx, url)
+                    .into_iter()
+                    .filter(|x| x.imports(ident))
+                    .find_map(|x| {
+                        self.scopes()
+                            .iter()
+                            .find(|y| {
+                                y.ident() == x.ident() && y.symbol_kind() == SymbolKind::PACKAGE
+                            })?
+                            .child(ident)
+                    })
+            }),
+        }
+    }
+
+    /// the package imports visible from the given location, those of the innermost scope
+    /// containing it first and those of the compilation unit last
+    fn package_imports(&self, byte_idx: usize, url: &Url) -> Vec<&dyn Definition> {
+        let mut imports: Vec<&dyn Definition> = Vec::new();
+        for scope in self.scopes() {
+            if &scope.url() == url && scope.start() <= byte_idx && byte_idx <= scope.end() {
+                imports = scope.package_imports(byte_idx, url);
+                break;
+            }
+        }
+        imports.extend(
+            self.defs()
+                .iter()
+                .filter(|x| &x.url() == url && x.symbol_kind() == SymbolKind::NAMESPACE)
+                .map(|x| x.as_ref()),
+        );
+        imports
+    }
+
+    /// a definition or scope directly within this scope
+    fn child(&self, ident: &str) -> Option<&dyn Definition> {
+        if let Some(def) = self.defs().iter().find(|x| x.ident() == ident) {
+            return Some(def.as_ref());
+        }
+        self.scopes()
+            .iter()
+            .find(|x| x.ident() == ident)
+            .map(|x| x.as_ref() as &dyn Definition)
+    }
+
+    /// return completions for the members of a hierarchical name such as `req.inner`, given as
+    /// a path of identifiers. This function should be called on the global scope
+    fn get_member_completion(
+        &self,
+        path: &[String],
+        byte_idx: usize,
+        url: &Url,
+    ) -> Vec<CompletionItem> {
+        let mut scope = match self
+            .find_path(path, byte_idx, url)
+            .and_then(|def| self.member_scope(def))
+        {
+            Some(scope) => scope,
+            None => return Vec::new(),
+        };
+        let mut completions: Vec<CompletionItem> = Vec::new();
+        // include members inherited from base classes, unless they are overridden
+        for _ in 0..MAX_INHERITANCE_DEPTH {
+            let members = scope
+                .defs()
+                .iter()
+                .map(|x| x.completion())
+                .chain(scope.scopes().iter().map(|x| x.completion()))
+                .filter(|x| !completions.iter().any(|y| y.label == x.label))
+                .collect::<Vec<CompletionItem>>();
+            completions.extend(members);
+            scope = match self.base_class(scope) {
+                Some(base) => base,
+                None => break,
+            };
+        }
+        completions
+    }
+
+    /// return completions for the members of a package, or the static members and types of a
+    /// class, named by a path such as [pkg, cls] for `pkg::cls::`. This function should be
+    /// called on the global scope
+    fn get_scoped_completion(&self, path: &[String]) -> Vec<CompletionItem> {
+        let mut scope = match path
+            .first()
+            .and_then(|x| self.scopes().iter().find(|y| &y.ident() == x))
+        {
+            Some(scope) => scope.as_ref(),
+            None => return Vec::new(),
+        };
+        for ident in &path[1..] {
+            scope = match scope.child(ident).and_then(|x| x.as_scope()) {
+                Some(scope) => scope,
+                None => return Vec::new(),
+            };
+        }
+        if scope.symbol_kind() == SymbolKind::PACKAGE {
+            return scope
+                .defs()
+                .iter()
+                .filter(|x| x.symbol_kind() != SymbolKind::NAMESPACE)
+                .map(|x| x.completion())
+                .chain(scope.scopes().iter().map(|x| x.completion()))
+                .collect();
+        }
+        let mut completions: Vec<CompletionItem> = Vec::new();
+        if scope.def_type() != DefinitionType::Class {
+            return completions;
+        }
+        // include static members inherited from base classes, unless they are overridden
+        for _ in 0..MAX_INHERITANCE_DEPTH {
+            let members = scope
+                .defs()
+                .iter()
+                .map(|x| x.as_ref())
+                .chain(scope.scopes().iter().map(|x| x.as_ref() as &dyn Definition))
+                .filter(|x| is_class_scoped(*x))
+                .map(|x| x.completion())
+                .filter(|x| !completions.iter().any(|y| y.label == x.label))
+                .collect::<Vec<CompletionItem>>();
+            completions.extend(members);
+            scope = match self.base_class(scope) {
+                Some(base) => base,
+                None => break,
+            };
+        }
+        completions
+    }
+
+    /// return completions for the literals of the enum type of the definition at the given
+    /// hierarchical name, ex. the literals of state_t for `state` in `state == `. This function
+    /// should be called on the global scope
+    fn get_enum_completion(
+        &self,
+        path: &[String],
+        byte_idx: usize,
+        url: &Url,
+    ) -> Vec<CompletionItem> {
+        let mut def = match self.find_path(path, byte_idx, url) {
+            Some(def) => def,
+            None => return Vec::new(),
+        };
+        // follow typedef aliases, with a limit in case of circular typedefs
+        for _ in 0..MAX_TYPEDEF_DEPTH {
+            let (package, type_ident) = match type_name(&def.type_str(), &def.ident()) {
+                Some(name) => name,
+                None => break,
+            };
+            def = match self.find_qualified(
+                package.as_deref(),
+                &type_ident,
+                def.byte_idx(),
+                &def.url(),
+            ) {
+                Some(def) => def,
+                None => break,
+            };
+            if def.completion_kind() == CompletionItemKind::ENUM {
+                return self
+                    .enum_literals(def)
+                    .iter()
+                    .map(|x| {
+                        let mut completion = x.completion();
+                        // literals of a package enum are qualified, as they may not be imported
+                        if let Some(package) = &package {
+                            completion.filter_text = Some(completion.label.clone());
+                            completion.label = format!("{}::{}", package, completion.label);
+                        }
+                        completion
+                    })
+                    .collect();
+            }
+        }
+        Vec::new()
+    }
+
+    /// the literals of an enum type, these are found alongside the type in the scope it is
+    /// declared in
+    fn enum_literals(&self, enum_type: &dyn Definition) -> Vec<&dyn Definition> {
+        let is_type = |x: &dyn Definition| {
+            x.ident() == enum_type.ident()
+                && x.byte_idx() == enum_type.byte_idx()
+                && x.url() == enum_type.url()
+        };
+        if self.scopes().iter().any(|x| is_type(x.as_ref())) {
+            return self
+                .defs()
+                .iter()
+                .filter(|x| {
+                    x.completion_kind() == CompletionItemKind::ENUM_MEMBER
+                        && x.type_str().split_whitespace().next() == Some(&enum_type.ident())
+                })
+                .map(|x| x.as_ref())
+                .collect();
+        }
+        for scope in self.scopes() {
+            if scope.url() == enum_type.url()
+                && scope.start() <= enum_type.byte_idx()
+                && enum_type.byte_idx() <= scope.end()
+            {
+                return scope.enum_literals(enum_type);
+            }
+        }
+        Vec::new()
+    }
+
     /// returns all symbols in a document
     fn document_symbols(&self, uri: &Url, doc: &Rope) -> Vec<DocumentSymbol> {
         let mut symbols: Vec<DocumentSymbol> = Vec::new();
@@ -288,7 +1102,7 @@ pub trait Scope: std::fmt::Debug + Definition + Sync + Send {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DefinitionType {
     Port,
     Net,
@@ -298,6 +1112,9 @@ pub enum DefinitionType {
     ModuleInstantiation,
     GenericScope,
     Class,
+    Macro,
+    // a declaration of something defined elsewhere, ex. a forward typedef or a DPI export
+    Declaration,
 }
 
 #[derive(Debug)]
@@ -359,6 +1176,7 @@ impl Definition for PortDec {
             label: self.ident.clone(),
             detail: Some(clean_type_str(&self.type_str, &self.ident)),
             kind: Some(self.completion_kind),
+            data: completion_data(&self.url, self.byte_idx),
             ..CompletionItem::default()
         }
     }
@@ -445,6 +1263,7 @@ impl Definition for GenericDec {
             label: self.ident.clone(),
             detail: Some(clean_type_str(&self.type_str, &self.ident)),
             kind: Some(self.completion_kind),
+            data: completion_data(&self.url, self.byte_idx),
             ..CompletionItem::default()
         }
     }
@@ -512,12 +1331,16 @@ impl Definition for PackageImport {
             label: self.ident.clone(),
             detail: Some(clean_type_str(&self.type_str, &self.ident.clone())),
             kind: Some(self.completion_kind),
+            data: completion_data(&self.url, self.byte_idx),
             ..CompletionItem::default()
         }
     }
     fn dot_completion(&self, _: &GenericScope) -> Vec<CompletionItem> {
         Vec::new()
     }
+    fn imports(&self, ident: &str) -> bool {
+        self.asterisk || self.import_ident.as_deref() == Some(ident)
+    }
 }
 
 #[derive(Debug)]
@@ -533,6 +1356,8 @@ pub struct SubDec {
     pub end: usize,
     pub defs: Vec<Box<dyn Definition>>,
     pub scopes: Vec<Box<dyn Scope>>,
+    // class of an out-of-block method body
+    pub class_scope: Option<String>,
 }
 
 impl SubDec {
@@ -549,6 +1374,7 @@ impl SubDec {
             end: 0,
             defs: Vec::new(),
             scopes: Vec::new(),
+            class_scope: None,
         }
     }
 }
@@ -583,12 +1409,19 @@ impl Definition for SubDec {
             label: self.ident.clone(),
             detail: Some(clean_type_str(&self.type_str, &self.ident)),
             kind: Some(self.completion_kind),
+            data: completion_data(&self.url, self.byte_idx),
             ..CompletionItem::default()
         }
     }
     fn dot_completion(&self, _: &GenericScope) -> Vec<CompletionItem> {
         Vec::new()
     }
+    fn as_scope(&self) -> Option<&dyn Scope> {
+        Some(self)
+    }
+    fn class_scope(&self) -> Option<String> {
+        self.class_scope.clone()
+    }
 }
 
 impl Scope for SubDec {
@@ -606,6 +1439,14 @@ impl Scope for SubDec {
     fn scopes(&self) -> &Vec<Box<dyn Scope>> {
         &self.scopes
     }
+
+    fn defs_mut(&mut self) -> &mut Vec<Box<dyn Definition>> {
+        &mut self.defs
+    }
+
+    fn scopes_mut(&mut self) -> &mut Vec<Box<dyn Scope>> {
+        &mut self.scopes
+    }
 }
 
 #[derive(Debug)]
@@ -665,6 +1506,7 @@ impl Definition for ModportDec {
             label: self.ident.clone(),
             detail: Some(clean_type_str(&self.type_str, &self.ident)),
             kind: Some(self.completion_kind),
+            data: completion_data(&self.url, self.byte_idx),
             ..CompletionItem::default()
         }
     }
@@ -683,6 +1525,7 @@ pub struct ModInst {
     pub symbol_kind: SymbolKind,
     pub def_type: DefinitionType,
     pub mod_ident: String,
+    pub params: Vec<(Option<String>, String)>,
 }
 
 impl ModInst {
@@ -696,6 +1539,7 @@ impl ModInst {
             symbol_kind: SymbolKind::MODULE,
             def_type: DefinitionType::ModuleInstantiation,
             mod_ident: String::new(),
+            params: Vec::new(),
         }
     }
 }
@@ -730,6 +1574,7 @@ impl Definition for ModInst {
             label: self.ident.clone(),
             detail: Some(clean_type_str(&self.type_str, &self.ident)),
             kind: Some(self.completion_kind),
+            data: completion_data(&self.url, self.byte_idx),
             ..CompletionItem::default()
         }
     }
@@ -746,6 +1591,12 @@ impl Definition for ModInst {
         }
         Vec::new()
     }
+    fn param_overrides(&self) -> Vec<(Option<String>, String)> {
+        self.params.clone()
+    }
+    fn mod_ident(&self) -> Option<String> {
+        Some(self.mod_ident.clone())
+    }
 }
 
 #[derive(Debug)]
@@ -821,21 +1672,22 @@ impl Definition for GenericScope {
             label: self.ident.clone(),
             detail: Some(clean_type_str(&self.type_str, &self.ident)),
             kind: Some(self.completion_kind),
+            data: completion_data(&self.url, self.byte_idx),
             ..CompletionItem::default()
         }
     }
-    fn dot_completion(&self, scope_tree: &GenericScope) -> Vec<CompletionItem> {
-        for scope in scope_tree.scopes() {
-            if scope.ident() == self.ident {
-                return scope
-                    .defs()
-                    .iter()
-                    .filter(|x| !x.starts_with(&scope.ident()))
-                    .map(|x| x.completion())
-                    .collect();
-            }
-        }
-        Vec::new()
+    fn dot_completion(&self, _: &GenericScope) -> Vec<CompletionItem> {
+        // complete from this scope's own members, so nested scopes such as generate blocks
+        // work as well as top level scopes
+        self.defs
+            .iter()
+            .filter(|x| !x.starts_with(&self.ident))
+            .map(|x| x.completion())
+            .chain(self.scopes.iter().map(|x| x.completion()))
+            .collect()
+    }
+    fn as_scope(&self) -> Option<&dyn Scope> {
+        Some(self)
     }
 }
 
@@ -855,6 +1707,14 @@ impl Scope for GenericScope {
     fn scopes(&self) -> &Vec<Box<dyn Scope>> {
         &self.scopes
     }
+
+    fn defs_mut(&mut self) -> &mut Vec<Box<dyn Definition>> {
+        &mut self.defs
+    }
+
+    fn scopes_mut(&mut self) -> &mut Vec<Box<dyn Scope>> {
+        &mut self.scopes
+    }
 }
 
 #[derive(Debug)]
@@ -926,6 +1786,7 @@ impl Definition for ClassDec {
             label: self.ident.clone(),
             detail: Some(clean_type_str(&self.type_str, &self.ident)),
             kind: Some(self.completion_kind),
+            data: completion_data(&self.url, self.byte_idx),
             ..CompletionItem::default()
         }
     }
@@ -942,6 +1803,9 @@ impl Definition for ClassDec {
         }
         Vec::new()
     }
+    fn as_scope(&self) -> Option<&dyn Scope> {
+        Some(self)
+    }
 }
 
 impl Scope for ClassDec {
@@ -960,4 +1824,23 @@ impl Scope for ClassDec {
     fn scopes(&self) -> &Vec<Box<dyn Scope>> {
         &self.scopes
     }
+
+    fn defs_mut(&mut self) -> &mut Vec<Box<dyn Definition>> {
+        &mut self.defs
+    }
+
+    fn scopes_mut(&mut self) -> &mut Vec<Box<dyn Scope>> {
+        &mut self.scopes
+    }
+
+    fn extends(&self) -> Option<(Option<String>, String)> {
+        Some((self.extends.1.clone(), self.extends.0.last()?.clone()))
+    }
+
+    fn implements(&self) -> Vec<(Option<String>, String)> {
+        self.implements
+            .iter()
+            .map(|(class, package)| (package.clone(), class.clone()))
+            .collect()
+    }
 }
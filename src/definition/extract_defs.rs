@@ -1,8 +1,15 @@
+use crate::definition::const_eval::{eval_const_expr, param_expr, ScopeEnv};
 use crate::definition::def_types::*;
-use crate::definition::match_definitions;
+use crate::definition::{match_definitions, ScopesAndDefs};
+use regex::Regex;
+use ropey::Rope;
+use std::collections::HashMap;
 use sv_parser::*;
 use tower_lsp::lsp_types::*;
 
+// most literals one enum literal range can declare
+const MAX_RANGE_LITERALS: u128 = 1024;
+
 pub fn get_ident(tree: &SyntaxTree, node: RefNode) -> (String, usize) {
     let loc = unwrap_locate!(node).unwrap();
     let ident_str = tree.get_str(loc).unwrap().to_string();
@@ -102,8 +109,17 @@ macro_rules! match_until_leave {
         let mut definitions: Vec<Box<dyn Definition>> = Vec::new();
         let mut global_scope: GenericScope = GenericScope::new($url);
         global_scope.ident = "global".to_string();
+        // number of generate constructs so far, used to name unnamed generate blocks
+        let mut generate_num: usize = 0;
         while let Some(event) = $event_iter.next() {
             match event {
+                NodeEvent::Enter(RefNode::LoopGenerateConstruct(_))
+                | NodeEvent::Enter(RefNode::ConditionalGenerateConstruct(_)) => {
+                    generate_num += 1;
+                    let mut result = generate_construct($tree, $event_iter, $url, generate_num)?;
+                    definitions.append(&mut result.1);
+                    scopes.append(&mut result.0);
+                }
                 NodeEvent::Enter(node) => {
                     let mut result = match_definitions($tree, $event_iter, node, $url)?;
                     definitions.append(&mut result.1);
@@ -640,17 +656,63 @@ fn data_type(
             scope.end = get_loc(tree, RefNode::Symbol(&node.nodes.2.nodes.2));
             scope.completion_kind = CompletionItemKind::ENUM;
             scope.symbol_kind = SymbolKind::ENUM;
-            let mut decs: Vec<GenericDec> = Vec::new();
+            // the value expression of the previous literal, literals without an explicit value
+            // are one more than the previous literal
+            let mut prev: Option<String> = None;
             for emem in node.nodes.2.nodes.1.contents() {
-                let mut dec = GenericDec::new(url);
                 let ident = get_ident(tree, RefNode::EnumIdentifier(&emem.nodes.0));
-                dec.ident = ident.0;
-                dec.byte_idx = ident.1;
-                dec.completion_kind = CompletionItemKind::ENUM_MEMBER;
-                dec.symbol_kind = SymbolKind::ENUM_MEMBER;
-                let tokens = &mut dec.type_str;
+                let mut tokens = String::new();
                 advance_until_leave!(tokens, tree, event_iter, RefNode::EnumNameDeclaration);
-                decs.push(dec);
+                // a range such as name[N] or name[N:M] declares a sequence of literals, ranges
+                // which aren't plain numbers are expanded once the scope tree is built
+                let idents = match &emem.nodes.1 {
+                    Some(range) => {
+                        let first = tree.get_str(&(range.nodes.1).0)?.trim();
+                        let last = match &(range.nodes.1).1 {
+                            Some((_, last)) => Some(tree.get_str(last)?.trim()),
+                            None => None,
+                        };
+                        let bounds = match last {
+                            Some(last) => last.parse::<i128>().ok().map(Some),
+                            None => Some(None),
+                        };
+                        let names = match (first.parse::<i128>().ok(), bounds) {
+                            (Some(first), Some(last)) => range_literals(&ident.0, first, last),
+                            _ => None,
+                        };
+                        match names {
+                            Some(names) => names,
+                            None => match last {
+                                Some(last) => vec![format!("{}[{}:{}]", ident.0, first, last)],
+                                None => vec![format!("{}[{}]", ident.0, first)],
+                            },
+                        }
+                    }
+                    None => vec![ident.0],
+                };
+                let mut value = match &emem.nodes.2 {
+                    Some((_, expr)) => Some(tree.get_str(expr)?.trim().to_string()),
+                    None => None,
+                };
+                for literal in idents {
+                    let mut dec = GenericDec::new(url);
+                    let expr = match (value.take(), &prev) {
+                        (Some(expr), _) => expr,
+                        (None, Some(prev)) => format!("{} + 1", prev),
+                        (None, None) => "0".to_string(),
+                    };
+                    dec.type_str = format!("{} = {}", literal, expr);
+                    // an unexpanded range is named by its base name
+                    dec.ident = match literal.split_once('[') {
+                        Some((base, _)) => base.to_owned(),
+                        None => literal,
+                    };
+                    dec.byte_idx = ident.1;
+                    dec.completion_kind = CompletionItemKind::ENUM_MEMBER;
+                    dec.symbol_kind = SymbolKind::ENUM_MEMBER;
+                    prev = Some(dec.ident.clone());
+                    scope.defs.push(Box::new(dec));
+                }
             }
             advance_until_leave!(common, tree, event_iter, RefNode::DataType)?;
             Some(Declaration::Scope(scope))
@@ -674,6 +736,179 @@ fn data_type(
     }
 }
 
+/// the names of the literals declared by an enum literal range, given its bounds, ex. S0, S1
+/// for S[2] or S5, S4 for S[5:4]
+fn range_literals(ident: &str, first: i128, last: Option<i128>) -> Option<Vec<String>> {
+    let (first, last) = match last {
+        Some(last) => (first, last),
+        // a count of literals from 0
+        None => (0, first.checked_sub(1)?),
+    };
+    if first < 0 || last < 0 || first.abs_diff(last) >= MAX_RANGE_LITERALS {
+        return None;
+    }
+    let range: Vec<i128> = if first <= last {
+        (first..=last).collect()
+    } else {
+        (last..=first).rev().collect()
+    };
+    Some(range.iter().map(|i| format!("{}{}", ident, i)).collect())
+}
+
+/// the bounds of an enum literal range which wasn't expanded while extracting, ex. 4'd2 of
+/// S[4'd2]
+fn unexpanded_range(def: &dyn Definition) -> Option<(String, Option<String>)> {
+    if def.completion_kind() != CompletionItemKind::ENUM_MEMBER {
+        return None;
+    }
+    let type_str = def.type_str();
+    let (decl, _) = param_expr(&type_str)?;
+    let open = decl.find(&format!("{}[", def.ident()))? + def.ident().len() + 1;
+    let range = &decl[open..open + decl[open..].rfind(']')?];
+    // the colon separating the bounds, rather than one of a package scope
+    let bytes = range.as_bytes();
+    let colon = (0..bytes.len()).find(|&i| {
+        bytes[i] == b':' && bytes.get(i + 1) != Some(&b':') && (i == 0 || bytes[i - 1] != b':')
+    });
+    Some(match colon {
+        Some(colon) => (
+            range[..colon].trim().to_owned(),
+            Some(range[colon + 1..].trim().to_owned()),
+        ),
+        None => (range.trim().to_owned(), None),
+    })
+}
+
+/// find the literals of the unexpanded enum literal ranges in a scope, with their bounds
+/// evaluated where the enum is declared
+fn range_expansions(
+    scope_tree: &GenericScope,
+    scope: &dyn Scope,
+    expansions: &mut HashMap<(Url, usize), Vec<String>>,
+) {
+    let no_overrides = HashMap::new();
+    for def in scope.defs() {
+        if let Some((first, last)) = unexpanded_range(def.as_ref()) {
+            let env = ScopeEnv::new(scope_tree, &def.url(), def.byte_idx(), &no_overrides);
+            let first = eval_const_expr(&first, &env).map(|x| x.value);
+            let last = match &last {
+                Some(last) => eval_const_expr(last, &env).map(|x| Some(x.value)),
+                None => Some(None),
+            };
+            if let (Some(first), Some(last)) = (first, last) {
+                if let Some(names) = range_literals(&def.ident(), first, last) {
+                    expansions.insert((def.url(), def.byte_idx()), names);
+                }
+            }
+        }
+    }
+    for scope in scope.scopes() {
+        range_expansions(scope_tree, scope.as_ref(), expansions);
+    }
+}
+
+/// replace the unexpanded enum literal ranges in a scope with the literals they declare
+fn expand_ranges(scope: &mut dyn Scope, expansions: &HashMap<(Url, usize), Vec<String>>) {
+    let defs = scope.defs_mut();
+    let mut idx = 0;
+    while idx < defs.len() {
+        let def = &defs[idx];
+        let names = match expansions.get(&(def.url(), def.byte_idx())) {
+            Some(names) if unexpanded_range(def.as_ref()).is_some() => names,
+            _ => {
+                idx += 1;
+                continue;
+            }
+        };
+        let type_str = def.type_str();
+        let (decl, expr) = match param_expr(&type_str) {
+            Some(split) => split,
+            None => {
+                idx += 1;
+                continue;
+            }
+        };
+        let def = defs.remove(idx);
+        let base = def.ident();
+        // the type the literals belong to
+        let prefix = decl.split(&format!("{}[", base)).next().unwrap_or_default();
+        let literal = |ident: &str, type_str: String| -> Box<dyn Definition> {
+            Box::new(GenericDec {
+                ident: ident.to_owned(),
+                byte_idx: def.byte_idx(),
+                url: def.url(),
+                type_str,
+                completion_kind: def.completion_kind(),
+                symbol_kind: def.symbol_kind(),
+                def_type: def.def_type(),
+            })
+        };
+        for (i, name) in names.iter().enumerate() {
+            let expr = match i {
+                0 => expr.to_owned(),
+                _ => format!("{} + 1", names[i - 1]),
+            };
+            defs.insert(idx, literal(name, format!("{}{} = {}", prefix, name, expr)));
+            idx += 1;
+        }
+        // the literal after the range counts on from its last literal
+        if let (Some(next), Some(last)) = (defs.get(idx), names.last()) {
+            let next_type = next.type_str();
+            if next.completion_kind() == CompletionItemKind::ENUM_MEMBER {
+                if let Some(next_decl) = next_type.strip_suffix(&format!("= {} + 1", base)) {
+                    let next_ident = next.ident();
+                    let next_def = Box::new(GenericDec {
+                        ident: next_ident,
+                        byte_idx: next.byte_idx(),
+                        url: next.url(),
+                        type_str: format!("{}= {} + 1", next_decl, last),
+                        completion_kind: next.completion_kind(),
+                        symbol_kind: next.symbol_kind(),
+                        def_type: next.def_type(),
+                    });
+                    defs[idx] = next_def;
+                }
+            }
+        }
+    }
+    for scope in scope.scopes_mut() {
+        expand_ranges(scope.as_mut(), expansions);
+    }
+}
+
+/// expand the enum literal ranges whose bounds aren't plain decimal numbers, ex. S['h2], by
+/// evaluating them where the enum is declared, those which can't be evaluated keep their base
+/// name
+pub fn expand_enum_ranges(scope_tree: &mut GenericScope) {
+    let mut expansions = HashMap::new();
+    range_expansions(scope_tree, scope_tree, &mut expansions);
+    if !expansions.is_empty() {
+        expand_ranges(scope_tree, &expansions);
+    }
+}
+
+/// enum literals are visible in the scope the enum is declared in, so they are moved out of
+/// the enum's scope, prefixed with the type they belong to
+fn enum_literals(scope: &mut GenericScope, type_ident: &str) -> Vec<Declaration> {
+    if scope.completion_kind != CompletionItemKind::ENUM {
+        return Vec::new();
+    }
+    std::mem::take(&mut scope.defs)
+        .into_iter()
+        .map(|def| {
+            Declaration::Dec(GenericDec {
+                ident: def.ident(),
+                byte_idx: def.byte_idx(),
+                url: def.url(),
+                type_str: format!("{} {}", type_ident, def.type_str()),
+                completion_kind: def.completion_kind(),
+                symbol_kind: def.symbol_kind(),
+                def_type: def.def_type(),
+            })
+        })
+        .collect()
+}
+
 pub fn data_dec(
     tree: &SyntaxTree,
     node: &DataDeclaration,
@@ -708,7 +943,8 @@ pub fn data_dec(
                             data.push(Declaration::Dec(var));
                         }
                     }
-                    Declaration::Scope(x) => {
+                    Declaration::Scope(mut x) => {
+                        data.append(&mut enum_literals(&mut x, "enum"));
                         let var_list = advance_until_enter!(
                             common,
                             tree,
@@ -801,7 +1037,10 @@ pub fn data_dec(
                             );
                         }
                         def.type_str = format!("{} {}", common, def.type_str);
+                        let ident = def.ident.clone();
+                        let mut literals = enum_literals(&mut def, &ident);
                         data = vec![Declaration::Scope(def)];
+                        data.append(&mut literals);
                     }
                     Declaration::Import(_) => unreachable!(),
                 }
@@ -832,6 +1071,7 @@ pub fn data_dec(
                 var.symbol_kind = SymbolKind::INTERFACE;
                 data = vec![Declaration::Dec(var)];
             }
+            // forward typedef, ex. typedef class C;
             TypeDeclaration::Reserved(y) => {
                 let mut var = GenericDec::new(url);
                 let ident = get_ident(tree, RefNode::TypeIdentifier(&y.nodes.2));
@@ -847,8 +1087,19 @@ pub fn data_dec(
                 );
                 var.type_str = tokens;
                 var.type_str = format!("{} {}", common, var.type_str);
-                var.completion_kind = CompletionItemKind::VARIABLE;
-                var.symbol_kind = SymbolKind::VARIABLE;
+                let (completion_kind, symbol_kind) = match &y.nodes.1 {
+                    Some(TypeDeclarationKeyword::Enum(_)) => {
+                        (CompletionItemKind::ENUM, SymbolKind::ENUM)
+                    }
+                    Some(TypeDeclarationKeyword::Struct(_))
+                    | Some(TypeDeclarationKeyword::Union(_)) => {
+                        (CompletionItemKind::STRUCT, SymbolKind::STRUCT)
+                    }
+                    _ => (CompletionItemKind::CLASS, SymbolKind::CLASS),
+                };
+                var.completion_kind = completion_kind;
+                var.symbol_kind = symbol_kind;
+                var.def_type = DefinitionType::Declaration;
                 data = vec![Declaration::Dec(var)];
             }
         },
@@ -927,21 +1178,39 @@ pub fn tfport_list(
 ) -> Option<Vec<PortDec>> {
     let mut tfports: Vec<PortDec> = Vec::new();
     for tfports_def in node.nodes.0.contents() {
+        // the text of the whole argument, with its direction, type and default value
+        let mut tokens = String::new();
+        advance_until_leave!(tokens, tree, event_iter, RefNode::TfPortItem);
         if let Some(def) = &tfports_def.nodes.4 {
             let mut tfport = PortDec::new(url);
             let ident = get_ident(tree, RefNode::PortIdentifier(&def.0));
             tfport.ident = ident.0;
             tfport.byte_idx = ident.1;
-            for _ in &def.1 {
-                let tokens = &mut tfport.type_str;
-                advance_until_leave!(tokens, tree, event_iter, RefNode::UnpackedDimension);
-            }
+            tfport.type_str = tokens.trim_start().trim_start_matches(',').to_owned();
             tfports.push(tfport);
         }
     }
     Some(tfports)
 }
 
+/// the class of an out-of-block method body, the last class of a scope such as `pkg::cls::`
+fn class_scope(
+    tree: &SyntaxTree,
+    node: &Option<InterfaceIdentifierOrClassScope>,
+) -> Option<String> {
+    match node {
+        Some(InterfaceIdentifierOrClassScope::ClassScope(x)) => {
+            let class_type = &x.nodes.0;
+            let ident = match class_type.nodes.2.last() {
+                Some((_, ident, _)) => ident,
+                None => &class_type.nodes.0.nodes.1,
+            };
+            Some(get_ident(tree, RefNode::ClassIdentifier(ident)).0)
+        }
+        _ => None,
+    }
+}
+
 pub fn function_dec(
     tree: &SyntaxTree,
     node: &FunctionDeclaration,
@@ -956,6 +1225,7 @@ pub fn function_dec(
             let ident = get_ident(tree, RefNode::FunctionIdentifier(&x.nodes.2));
             func.ident = ident.0;
             func.byte_idx = ident.1;
+            func.class_scope = class_scope(tree, &x.nodes.1);
             let mut tokens = String::new();
             advance_until_enter!(
                 tokens,
@@ -971,6 +1241,7 @@ pub fn function_dec(
             let ident = get_ident(tree, RefNode::FunctionIdentifier(&x.nodes.2));
             func.ident = ident.0;
             func.byte_idx = ident.1;
+            func.class_scope = class_scope(tree, &x.nodes.1);
             let mut tokens = String::new();
             advance_until_enter!(
                 tokens,
@@ -996,6 +1267,46 @@ pub fn function_dec(
     Some(func)
 }
 
+pub fn constructor_dec(
+    tree: &SyntaxTree,
+    node: &ClassConstructorDeclaration,
+    event_iter: &mut EventIter,
+    url: &Url,
+) -> Option<SubDec> {
+    let mut func: SubDec = SubDec::new(url);
+    func.start = get_loc(tree, RefNode::Keyword(&node.nodes.0));
+    func.end = get_loc(tree, RefNode::Keyword(&node.nodes.8));
+    func.ident = "new".to_owned();
+    func.byte_idx = get_loc(tree, RefNode::Keyword(&node.nodes.2));
+    func.class_scope = node.nodes.1.as_ref().map(|x| {
+        let class_type = &x.nodes.0;
+        let ident = match class_type.nodes.2.last() {
+            Some((_, ident, _)) => ident,
+            None => &class_type.nodes.0.nodes.1,
+        };
+        get_ident(tree, RefNode::ClassIdentifier(ident)).0
+    });
+    let mut tokens = String::new();
+    advance_until_leave!(tokens, tree, event_iter, RefNode::Keyword);
+    func.type_str = tokens;
+    func.completion_kind = CompletionItemKind::CONSTRUCTOR;
+    func.symbol_kind = SymbolKind::CONSTRUCTOR;
+    if let Some(Paren {
+        nodes: (_, Some(tfports), _),
+    }) = &node.nodes.3
+    {
+        skip_until_enter!(tree, event_iter, RefNode::TfPortList, &TfPortList);
+        for port in tfport_list(tree, tfports, event_iter, url)? {
+            func.defs.push(Box::new(port));
+        }
+    }
+    let (scopes, mut defs) =
+        match_until_leave!(tree, event_iter, url, RefNode::ClassConstructorDeclaration)?;
+    func.scopes = scopes;
+    func.defs.append(&mut defs);
+    Some(func)
+}
+
 pub fn task_dec(
     tree: &SyntaxTree,
     node: &TaskDeclaration,
@@ -1010,6 +1321,7 @@ pub fn task_dec(
             let ident = get_ident(tree, RefNode::TaskIdentifier(&x.nodes.1));
             task.ident = ident.0;
             task.byte_idx = ident.1;
+            task.class_scope = class_scope(tree, &x.nodes.0);
             let mut tokens = String::new();
             advance_until_enter!(
                 tokens,
@@ -1022,10 +1334,10 @@ pub fn task_dec(
         }
         TaskBodyDeclaration::WithPort(x) => {
             task.end = get_loc(tree, RefNode::Keyword(&x.nodes.6));
-            let mut task = SubDec::new(url);
             let ident = get_ident(tree, RefNode::TaskIdentifier(&x.nodes.1));
             task.ident = ident.0;
             task.byte_idx = ident.1;
+            task.class_scope = class_scope(tree, &x.nodes.0);
             let mut tokens = String::new();
             advance_until_enter!(
                 tokens,
@@ -1050,6 +1362,126 @@ pub fn task_dec(
     Some(task)
 }
 
+/// a method prototype in a class, ex. `pure virtual function void m();` or `extern task t();`,
+/// its body is defined elsewhere
+pub fn method_prototype(
+    tree: &SyntaxTree,
+    node: &MethodPrototype,
+    event_iter: &mut EventIter,
+    url: &Url,
+    start: &Keyword,
+    end: &Symbol,
+) -> Option<SubDec> {
+    let mut method = SubDec::new(url);
+    method.start = get_loc(tree, RefNode::Keyword(start));
+    method.end = get_loc(tree, RefNode::Symbol(end));
+    let mut tokens = String::new();
+    let ports = match node {
+        MethodPrototype::TaskPrototype(x) => {
+            let ident = get_ident(tree, RefNode::TaskIdentifier(&x.nodes.1));
+            method.ident = ident.0;
+            method.byte_idx = ident.1;
+            advance_until_enter!(
+                tokens,
+                tree,
+                event_iter,
+                RefNode::TaskIdentifier,
+                &TaskIdentifier
+            );
+            &x.nodes.2
+        }
+        MethodPrototype::FunctionPrototype(x) => {
+            let ident = get_ident(tree, RefNode::FunctionIdentifier(&x.nodes.2));
+            method.ident = ident.0;
+            method.byte_idx = ident.1;
+            advance_until_enter!(
+                tokens,
+                tree,
+                event_iter,
+                RefNode::FunctionIdentifier,
+                &FunctionIdentifier
+            );
+            &x.nodes.3
+        }
+    };
+    method.type_str = tokens;
+    if let Some(Paren {
+        nodes: (_, Some(tfports), _),
+    }) = ports
+    {
+        skip_until_enter!(tree, event_iter, RefNode::TfPortList, &TfPortList);
+        for port in tfport_list(tree, tfports, event_iter, url)? {
+            method.defs.push(Box::new(port));
+        }
+    }
+    Some(method)
+}
+
+/// DPI imported function or task, ex. import "DPI-C" function int c_add(int a, int b);
+pub fn dpi_import(
+    tree: &SyntaxTree,
+    node: &DpiImportExport,
+    event_iter: &mut EventIter,
+    url: &Url,
+) -> Option<SubDec> {
+    // the prototype of an import is the same as that of a class method
+    let (prototype, start, end) = match node {
+        DpiImportExport::ImportFunction(x) => (
+            MethodPrototype::FunctionPrototype(Box::new(x.nodes.4.nodes.0.clone())),
+            &x.nodes.0,
+            &x.nodes.5,
+        ),
+        DpiImportExport::ImportTask(x) => (
+            MethodPrototype::TaskPrototype(Box::new(x.nodes.4.nodes.0.clone())),
+            &x.nodes.0,
+            &x.nodes.5,
+        ),
+        _ => return None,
+    };
+    method_prototype(tree, &prototype, event_iter, url, start, end)
+}
+
+/// DPI exported function or task, ex. export "DPI-C" function sv_add;
+pub fn dpi_export(
+    tree: &SyntaxTree,
+    node: &DpiImportExport,
+    event_iter: &mut EventIter,
+    url: &Url,
+) -> Option<GenericDec> {
+    let mut export = GenericDec::new(url);
+    let mut tokens = String::new();
+    let ident = match node {
+        DpiImportExport::ExportFunction(x) => {
+            advance_until_enter!(
+                tokens,
+                tree,
+                event_iter,
+                RefNode::FunctionIdentifier,
+                &FunctionIdentifier
+            );
+            get_ident(tree, RefNode::FunctionIdentifier(&x.nodes.4))
+        }
+        DpiImportExport::ExportTask(x) => {
+            advance_until_enter!(
+                tokens,
+                tree,
+                event_iter,
+                RefNode::TaskIdentifier,
+                &TaskIdentifier
+            );
+            get_ident(tree, RefNode::TaskIdentifier(&x.nodes.4))
+        }
+        _ => return None,
+    };
+    export.ident = ident.0;
+    export.byte_idx = ident.1;
+    export.type_str = tokens;
+    export.completion_kind = CompletionItemKind::FUNCTION;
+    export.symbol_kind = SymbolKind::FUNCTION;
+    export.def_type = DefinitionType::Declaration;
+    Some(export)
+}
+
 pub fn modport_dec(
     tree: &SyntaxTree,
     node: &ModportDeclaration,
@@ -1227,6 +1659,27 @@ pub fn module_inst(
 ) -> Option<Vec<ModInst>> {
     let mut defs: Vec<ModInst> = Vec::new();
     let mod_ident = get_ident(tree, RefNode::ModuleIdentifier(&node.nodes.0)).0;
+    let mut params: Vec<(Option<String>, String)> = Vec::new();
+    if let Some(param_assigns) = &node.nodes.1 {
+        match &param_assigns.nodes.1.nodes.1 {
+            Some(ListOfParameterAssignments::Ordered(x)) => {
+                for param in x.nodes.0.contents() {
+                    params.push((None, tree.get_str(param)?.trim().to_string()));
+                }
+            }
+            Some(ListOfParameterAssignments::Named(x)) => {
+                for param in x.nodes.0.contents() {
+                    let ident = get_ident(tree, RefNode::ParameterIdentifier(&param.nodes.1)).0;
+                    let expr = match &param.nodes.2.nodes.1 {
+                        Some(expr) => tree.get_str(expr)?.trim().to_string(),
+                        None => String::new(),
+                    };
+                    params.push((Some(ident), expr));
+                }
+            }
+            None => (),
+        }
+    }
     for _ in node.nodes.2.contents() {
         let hinst = skip_until_enter!(
             tree,
@@ -1240,6 +1693,7 @@ pub fn module_inst(
         instance.byte_idx = ident.1;
         instance.type_str = mod_ident.clone();
         instance.mod_ident = mod_ident.clone();
+        instance.params = params.clone();
         let type_str = &mut instance.type_str;
         for _ in &hinst.nodes.0.nodes.1 {
             advance_until_leave!(type_str, tree, event_iter, RefNode::UnpackedDimension);
@@ -2274,6 +2728,48 @@ pub fn class_dec(
     Some(scope)
 }
 
+pub fn interface_class_dec(
+    tree: &SyntaxTree,
+    node: &InterfaceClassDeclaration,
+    event_iter: &mut EventIter,
+    url: &Url,
+) -> Option<ClassDec> {
+    let mut scope: ClassDec = ClassDec::new(url);
+    scope.start = get_loc(tree, RefNode::Keyword(&node.nodes.0));
+    scope.end = get_loc(tree, RefNode::Keyword(&node.nodes.7));
+    let ident = get_ident(tree, RefNode::ClassIdentifier(&node.nodes.2));
+    scope.ident = ident.0;
+    scope.byte_idx = ident.1;
+    scope.completion_kind = CompletionItemKind::INTERFACE;
+    scope.symbol_kind = SymbolKind::INTERFACE;
+    let type_str = &mut scope.type_str;
+    advance_until_leave!(type_str, tree, event_iter, RefNode::ClassIdentifier);
+    if let Some(pport_list) = &node.nodes.3 {
+        let pports = param_port_list(tree, pport_list, event_iter, url)?;
+        for pport in pports {
+            scope.defs.push(Box::new(pport));
+        }
+    }
+    // an interface class may extend several interface classes, these are kept as implements
+    if let Some((_, interfaces)) = &node.nodes.4 {
+        for interface in interfaces.contents() {
+            let ident = get_ident(tree, RefNode::ClassIdentifier(&interface.nodes.0.nodes.1));
+            let package = match &interface.nodes.0.nodes.0 {
+                Some(PackageScope::Package(x)) => {
+                    Some(get_ident(tree, RefNode::PackageIdentifier(&x.nodes.0)).0)
+                }
+                _ => None,
+            };
+            scope.implements.push((ident.0, package));
+        }
+    }
+    let (scopes, mut defs) =
+        match_until_leave!(tree, event_iter, url, RefNode::InterfaceClassDeclaration)?;
+    scope.scopes = scopes;
+    scope.defs.append(&mut defs);
+    Some(scope)
+}
+
 // `define definition
 pub fn text_macro_def(
     tree: &SyntaxTree,
@@ -2295,5 +2791,143 @@ pub fn text_macro_def(
     );
     text_macro.completion_kind = CompletionItemKind::FUNCTION;
     text_macro.symbol_kind = SymbolKind::FUNCTION;
+    text_macro.def_type = DefinitionType::Macro;
     Some(text_macro)
 }
+
+/// `define definitions, found in the text of the file since the preprocessor removes them from
+/// the syntax tree
+pub fn text_macro_defs(text: &Rope, url: &Url) -> Vec<GenericDec> {
+    static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"^\s*`define\s+([a-zA-Z_][a-zA-Z0-9_$]*)").unwrap());
+    let mut macros: Vec<GenericDec> = Vec::new();
+    for (line_idx, line) in text.lines().enumerate() {
+        let line = line.to_string();
+        if let Some(ident) = re.captures(&line).and_then(|x| x.get(1)) {
+            let mut text_macro = GenericDec::new(url);
+            text_macro.ident = ident.as_str().to_owned();
+            text_macro.byte_idx = text.line_to_byte(line_idx) + ident.start();
+            text_macro.type_str = "`define".to_owned();
+            text_macro.completion_kind = CompletionItemKind::FUNCTION;
+            text_macro.symbol_kind = SymbolKind::FUNCTION;
+            text_macro.def_type = DefinitionType::Macro;
+            macros.push(text_macro);
+        }
+    }
+    macros
+}
+
+pub fn genvar_dec(
+    tree: &SyntaxTree,
+    node: &GenvarDeclaration,
+    _: &mut EventIter,
+    url: &Url,
+) -> Option<Vec<GenericDec>> {
+    let mut genvars: Vec<GenericDec> = Vec::new();
+    for genvar_ident in node.nodes.1.nodes.0.contents() {
+        let mut genvar = GenericDec::new(url);
+        let ident = get_ident(tree, RefNode::GenvarIdentifier(genvar_ident));
+        genvar.ident = ident.0;
+        genvar.byte_idx = ident.1;
+        genvar.type_str = "genvar".to_string();
+        genvar.symbol_kind = SymbolKind::VARIABLE;
+        genvars.push(genvar);
+    }
+    Some(genvars)
+}
+
+fn generate_block(
+    tree: &SyntaxTree,
+    node: &GenerateBlockMultiple,
+    event_iter: &mut EventIter,
+    url: &Url,
+    generate_num: usize,
+) -> Option<GenericScope> {
+    let mut scope: GenericScope = GenericScope::new(url);
+    scope.start = match &node.nodes.0 {
+        Some((label, _)) => get_loc(tree, RefNode::GenerateBlockIdentifier(label)),
+        None => get_loc(tree, RefNode::Keyword(&node.nodes.1)),
+    };
+    scope.end = get_loc(tree, RefNode::Keyword(&node.nodes.4));
+    let label = match (&node.nodes.0, &node.nodes.2) {
+        (Some((label, _)), _) | (None, Some((_, label))) => Some(label),
+        (None, None) => None,
+    };
+    match label {
+        Some(label) => {
+            let ident = get_ident(tree, RefNode::GenerateBlockIdentifier(label));
+            scope.ident = ident.0;
+            scope.byte_idx = ident.1;
+        }
+        // unnamed generate blocks are named after the generate construct they belong to,
+        // see IEEE 1800-2017 27.6
+        None => {
+            scope.ident = format!("genblk{}", generate_num);
+            scope.byte_idx = get_loc(tree, RefNode::Keyword(&node.nodes.1));
+        }
+    }
+    scope.type_str = "generate".to_string();
+
+    let (scopes, mut defs) =
+        match_until_leave!(tree, event_iter, url, RefNode::GenerateBlockMultiple)?;
+    scope.scopes = scopes;
+    scope.defs.append(&mut defs);
+    scope.completion_kind = CompletionItemKind::MODULE;
+    scope.symbol_kind = SymbolKind::NAMESPACE;
+    Some(scope)
+}
+
+/// extract the generate blocks of a loop or conditional generate construct, this should be
+/// called after entering the construct. An else if chain is a single construct, so nested
+/// constructs which aren't enclosed in a begin-end block share the generate number.
+fn generate_construct(
+    tree: &SyntaxTree,
+    event_iter: &mut EventIter,
+    url: &Url,
+    generate_num: usize,
+) -> ScopesAndDefs {
+    let mut scopes: Vec<Box<dyn Scope>> = Vec::new();
+    let mut definitions: Vec<Box<dyn Definition>> = Vec::new();
+    // a genvar declared in a loop header belongs to the loop's generate block
+    let mut genvar: Option<GenericDec> = None;
+    while let Some(event) = event_iter.next() {
+        match event {
+            NodeEvent::Enter(RefNode::GenvarInitialization(init)) => {
+                if init.nodes.0.is_some() {
+                    let mut dec = GenericDec::new(url);
+                    let ident = get_ident(tree, RefNode::GenvarIdentifier(&init.nodes.1));
+                    dec.ident = ident.0;
+                    dec.byte_idx = ident.1;
+                    dec.type_str = "genvar".to_string();
+                    dec.symbol_kind = SymbolKind::VARIABLE;
+                    genvar = Some(dec);
+                }
+            }
+            NodeEvent::Enter(RefNode::GenerateBlockMultiple(node)) => {
+                let mut block = generate_block(tree, node, event_iter, url, generate_num)?;
+                if let Some(genvar) = genvar.take() {
+                    block.defs.insert(0, Box::new(genvar));
+                }
+                scopes.push(Box::new(block));
+            }
+            NodeEvent::Enter(RefNode::LoopGenerateConstruct(_))
+            | NodeEvent::Enter(RefNode::ConditionalGenerateConstruct(_)) => {
+                let mut result = generate_construct(tree, event_iter, url, generate_num)?;
+                definitions.append(&mut result.1);
+                scopes.append(&mut result.0);
+            }
+            NodeEvent::Enter(node) => {
+                let mut result = match_definitions(tree, event_iter, node, url)?;
+                definitions.append(&mut result.1);
+                scopes.append(&mut result.0);
+            }
+            NodeEvent::Leave(RefNode::LoopGenerateConstruct(_))
+            | NodeEvent::Leave(RefNode::ConditionalGenerateConstruct(_)) => break,
+            NodeEvent::Leave(_) => (),
+        }
+    }
+    if let Some(genvar) = genvar {
+        definitions.push(Box::new(genvar));
+    }
+    Some((scopes, definitions))
+}
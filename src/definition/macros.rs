@@ -0,0 +1,177 @@
+use super::get_hover;
+use log::debug;
+use ropey::Rope;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use sv_parser::preprocess_str;
+use tower_lsp::lsp_types::*;
+
+const EXPANSION_START: &str = "__veridian_expansion_start__";
+const EXPANSION_END: &str = "__veridian_expansion_end__";
+
+/// the text of a `define, with its formal arguments and any continued lines
+pub fn macro_definition(doc: &Rope, byte_idx: usize) -> String {
+    let mut line_idx = doc.byte_to_line(byte_idx);
+    let mut hover = get_hover(doc, line_idx);
+    while hover.trim_end().ends_with('\\') && line_idx + 1 < doc.len_lines() {
+        line_idx += 1;
+        hover = format!("{}\n{}", hover.trim_end(), doc.line(line_idx));
+    }
+    hover.trim_end().to_owned()
+}
+
+/// the byte range of the macro usage at byte_idx, from the backtick to the end of the
+/// actual arguments if there are any
+fn macro_usage(text: &str, byte_idx: usize) -> Option<(usize, usize)> {
+    let is_ident = |c: u8| c.is_ascii_alphanumeric() || c == b'_';
+    let bytes = text.as_bytes();
+    let mut start = byte_idx.min(bytes.len());
+    while start > 0 && is_ident(bytes[start - 1]) {
+        start -= 1;
+    }
+    if start == 0 || bytes[start - 1] != b'`' {
+        return None;
+    }
+    start -= 1;
+    let mut end = byte_idx;
+    while end < bytes.len() && is_ident(bytes[end]) {
+        end += 1;
+    }
+    let mut args = end;
+    while args < bytes.len() && (bytes[args] == b' ' || bytes[args] == b'\t') {
+        args += 1;
+    }
+    if bytes.get(args) != Some(&b'(') {
+        return Some((start, end));
+    }
+    // find the closing paren of the actual arguments
+    let mut depth = 0;
+    let mut in_str = false;
+    for (idx, c) in bytes.iter().enumerate().skip(args) {
+        match c {
+            b'"' if idx == 0 || bytes[idx - 1] != b'\\' => in_str = !in_str,
+            _ if in_str => (),
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((start, idx + 1));
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// expand the macro usage at byte_idx using the preprocessor, with the macros defined in the
+/// file and its includes up to that point
+pub fn expand_macro(
+    doc: &Rope,
+    uri: &Url,
+    byte_idx: usize,
+    include_dirs: &[PathBuf],
+) -> Option<String> {
+    let text = doc.to_string();
+    let (start, end) = macro_usage(&text, byte_idx)?;
+    // mark the usage so its expansion can be found in the preprocessed text
+    let marked = format!(
+        "{} {} {} {} {}",
+        &text[..start],
+        EXPANSION_START,
+        &text[start..end],
+        EXPANSION_END,
+        &text[end..]
+    );
+    let path = uri.to_file_path().ok()?;
+    let mut include_dirs = include_dirs.to_vec();
+    include_dirs.extend(path.parent().map(|x| x.to_path_buf()));
+    let (preprocessed, _) = match preprocess_str(
+        &marked,
+        &path,
+        &HashMap::new(),
+        &include_dirs,
+        false,
+        false,
+        0,
+    ) {
+        Ok(result) => result,
+        Err(e) => {
+            debug!("macro expansion failed: {:?}", e);
+            return None;
+        }
+    };
+    let expanded = preprocessed.text();
+    let start = expanded.find(EXPANSION_START)? + EXPANSION_START.len();
+    let end = start + expanded[start..].find(EXPANSION_END)?;
+    Some(expanded[start..end].trim().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::LSPServer;
+    use crate::support::test_init;
+    use std::fs::read_to_string;
+
+    #[test]
+    fn test_macros() {
+        test_init();
+        let server = LSPServer::new(None);
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("test_data/macro_test.sv");
+        let uri = Url::from_file_path(&d).unwrap();
+        let header = d.with_file_name("macros.svh");
+        let header_uri = Url::from_file_path(&header).unwrap();
+        for (uri, path) in &[(&header_uri, &header), (&uri, &d)] {
+            server.did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: (*uri).clone(),
+                    language_id: "systemverilog".to_owned(),
+                    version: 0,
+                    text: read_to_string(path).unwrap(),
+                },
+            });
+            let fid = server.srcs.get_id(uri);
+            server.srcs.wait_parse_ready(fid, true);
+        }
+        let position = |line, character| TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            position: Position::new(line, character),
+        };
+        let hover = |line, character| {
+            let hover = server.hover(HoverParams {
+                text_document_position_params: position(line, character),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            });
+            match hover?.contents {
+                HoverContents::Scalar(MarkedString::LanguageString(x)) => Some(x.value),
+                _ => None,
+            }
+        };
+        assert_eq!(
+            hover(3, 4).unwrap(),
+            "`define PAIR(a, b) `REG(a) `REG(b, 4)\n\n// expands to\nlogic [8 -1:0] x; logic [4-1:0] y;"
+        );
+        // defined in the included file, with a continued line
+        assert_eq!(
+            hover(4, 5).unwrap(),
+            "// register of the given width\n`define REG(name, width = `WIDTH) \\\n  logic [width-1:0] name;\n\n// expands to\nlogic [8 -1:0] z;"
+        );
+        // the identifier declared by the usage isn't the macro
+        assert_eq!(hover(4, 7).unwrap(), "`REG(z)");
+
+        let goto = server.goto_definition(GotoDefinitionParams {
+            text_document_position_params: position(4, 4),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        });
+        assert_eq!(
+            goto,
+            Some(GotoDefinitionResponse::Scalar(Location::new(
+                header_uri,
+                Range::new(Position::new(2, 8), Position::new(2, 11))
+            )))
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use crate::definition::const_eval::{eval_const_expr, named_overrides, ConstValue, ScopeEnv};
+use crate::definition::def_types::*;
+use crate::server::LSPServer;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tower_lsp::lsp_types::*;
+
+/// parameters of the veridian/hierarchy request
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HierarchyParams {
+    // top module to elaborate from, overrides the configured top module
+    pub top: Option<String>,
+}
+
+/// a parameter override of a module instantiation
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HierarchyParam {
+    // None if the override is ordered and the instantiated module couldn't be found
+    pub name: Option<String>,
+    pub expression: String,
+    // the computed value of the expression, if it could be evaluated
+    pub value: Option<String>,
+}
+
+/// an instance in the elaborated design hierarchy
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HierarchyNode {
+    // instance name, or the module name for a top module
+    pub instance: String,
+    pub module: String,
+    pub location: Option<Location>,
+    pub parameters: Vec<HierarchyParam>,
+    pub children: Vec<HierarchyNode>,
+}
+
+impl LSPServer {
+    pub fn hierarchy(&self, params: HierarchyParams) -> Vec<HierarchyNode> {
+        let top = params
+            .top
+            .or_else(|| self.conf.read().unwrap().top_module.clone());
+        debug!("hierarchy requested, top: {:?}", top);
+        let scope_tree = self.srcs.scope_tree.read().unwrap();
+        let scope_tree = match scope_tree.as_ref() {
+            Some(tree) => tree,
+            None => return Vec::new(),
+        };
+        let tops: Vec<&dyn Scope> = match top {
+            Some(top) => scope_tree
+                .scopes()
+                .iter()
+                .filter(|x| x.ident() == top)
+                .map(|x| x.as_ref())
+                .collect(),
+            None => top_modules(scope_tree),
+        };
+        let no_overrides = HashMap::new();
+        tops.into_iter()
+            .map(|module| HierarchyNode {
+                instance: module.ident(),
+                module: module.ident(),
+                location: self.location(&module.url(), module.byte_idx(), &module.ident()),
+                parameters: Vec::new(),
+                children: self.elaborate(
+                    scope_tree,
+                    module,
+                    &no_overrides,
+                    &mut vec![module.ident()],
+                ),
+            })
+            .collect()
+    }
+
+    /// build the instance tree below a module elaborated with the given parameter values,
+    /// stack holds the modules currently being elaborated so that recursive instantiations
+    /// terminate
+    fn elaborate(
+        &self,
+        scope_tree: &GenericScope,
+        module: &dyn Scope,
+        overrides: &HashMap<String, ConstValue>,
+        stack: &mut Vec<String>,
+    ) -> Vec<HierarchyNode> {
+        let mut nodes: Vec<HierarchyNode> = Vec::new();
+        for inst in instances(module) {
+            let mod_ident = inst.mod_ident().unwrap_or_default();
+            let target = find_module(scope_tree, &mod_ident);
+            let env = ScopeEnv::new(scope_tree, &inst.url(), inst.byte_idx(), overrides);
+            // the parameter values the instantiated module is elaborated with
+            let mut inst_overrides = HashMap::new();
+            let parameters = named_overrides(inst, target)
+                .into_iter()
+                .map(|(name, expression)| {
+                    let value = eval_const_expr(&expression, &env);
+                    if let (Some(name), Some(value)) = (&name, value) {
+                        inst_overrides.insert(name.clone(), value);
+                    }
+                    HierarchyParam {
+                        name,
+                        value: value.map(|x| x.value.to_string()),
+                        expression,
+                    }
+                })
+                .collect();
+            let children = match target {
+                Some(target) if !stack.contains(&mod_ident) => {
+                    stack.push(mod_ident.clone());
+                    let children = self.elaborate(scope_tree, target, &inst_overrides, stack);
+                    stack.pop();
+                    children
+                }
+                _ => Vec::new(),
+            };
+            nodes.push(HierarchyNode {
+                instance: inst.ident(),
+                module: mod_ident,
+                location: self.location(&inst.url(), inst.byte_idx(), &inst.ident()),
+                parameters,
+                children,
+            });
+        }
+        nodes
+    }
+
+    fn location(&self, url: &Url, byte_idx: usize, ident: &str) -> Option<Location> {
+        Some(Location::new(
+            url.clone(),
+            Range::new(
+                self.srcs.byte_to_pos(url, byte_idx)?,
+                self.srcs.byte_to_pos(url, byte_idx + ident.len())?,
+            ),
+        ))
+    }
+}
+
+/// all module instantiations within a scope, including nested scopes
+pub fn instances(scope: &dyn Scope) -> Vec<&dyn Definition> {
+    let mut insts: Vec<&dyn Definition> = scope
+        .defs()
+        .iter()
+        .filter(|x| x.mod_ident().is_some())
+        .map(|x| x.as_ref())
+        .collect();
+    for child in scope.scopes() {
+        insts.append(&mut instances(child.as_ref()));
+    }
+    insts.sort_by_key(|x| x.byte_idx());
+    insts
+}
+
+/// find the module, interface or program with the given identifier
+fn find_module<'a>(scope_tree: &'a GenericScope, ident: &str) -> Option<&'a dyn Scope> {
+    scope_tree
+        .scopes()
+        .iter()
+        .find(|x| {
+            x.ident() == ident
+                && (x.symbol_kind() == SymbolKind::MODULE
+                    || x.symbol_kind() == SymbolKind::INTERFACE)
+        })
+        .map(|x| x.as_ref())
+}
+
+/// modules which are never instantiated, these are the tops of the design hierarchy
+fn top_modules(scope_tree: &GenericScope) -> Vec<&dyn Scope> {
+    let mut instantiated: Vec<String> = Vec::new();
+    for scope in scope_tree.scopes() {
+        for inst in instances(scope.as_ref()) {
+            instantiated.push(inst.mod_ident().unwrap_or_default());
+        }
+    }
+    scope_tree
+        .scopes()
+        .iter()
+        .filter(|x| {
+            let keyword = x.type_str();
+            let keyword = keyword.split_whitespace().next().unwrap_or_default();
+            (keyword == "module" || keyword == "macromodule") && !instantiated.contains(&x.ident())
+        })
+        .map(|x| x.as_ref())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::test_init;
+
+    fn open(server: &LSPServer, uri: &Url, text: &str) {
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+        let fid = server.srcs.get_id(uri);
+        server.srcs.wait_parse_ready(fid, true);
+    }
+
+    #[test]
+    fn test_hierarchy() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let uri2 = Url::parse("file:///test2.sv").unwrap();
+        let text = r#"module top;
+  localparam W = 4;
+  core #(.WIDTH(W * 2)) u_core ();
+  core u_core2 ();
+endmodule
+"#;
+        let text2 = r#"module core #(parameter WIDTH = 1) ();
+  alu #(WIDTH) u_alu ();
+endmodule
+module alu #(parameter N = 1) ();
+endmodule
+"#;
+        open(&server, &uri, text);
+        open(&server, &uri2, text2);
+
+        let tree = server.hierarchy(HierarchyParams::default());
+        assert_eq!(tree.len(), 1);
+        let top = &tree[0];
+        assert_eq!(top.module, "top");
+        let insts: Vec<&str> = top.children.iter().map(|x| x.instance.as_str()).collect();
+        assert_eq!(insts, vec!["u_core", "u_core2"]);
+
+        let u_core = &top.children[0];
+        assert_eq!(u_core.module, "core");
+        assert_eq!(
+            u_core.location,
+            Some(Location::new(
+                uri,
+                Range::new(Position::new(2, 24), Position::new(2, 30))
+            ))
+        );
+        assert_eq!(
+            u_core.parameters,
+            vec![HierarchyParam {
+                name: Some("WIDTH".to_string()),
+                expression: "W * 2".to_string(),
+                value: Some("8".to_string()),
+            }]
+        );
+        let u_alu = &u_core.children[0];
+        assert_eq!(u_alu.module, "alu");
+        assert_eq!(u_alu.parameters[0].name, Some("N".to_string()));
+        assert_eq!(u_alu.location.as_ref().unwrap().uri, uri2);
+        // evaluated with the parameters its parent instance was elaborated with
+        assert_eq!(u_alu.parameters[0].value, Some("8".to_string()));
+        let u_alu2 = &top.children[1].children[0];
+        assert_eq!(u_alu2.parameters[0].value, Some("1".to_string()));
+
+        let tree = server.hierarchy(HierarchyParams {
+            top: Some("core".to_string()),
+        });
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].children[0].instance, "u_alu");
+    }
+}
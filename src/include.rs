@@ -0,0 +1,340 @@
+use crate::server::LSPServer;
+use log::debug;
+use regex::Regex;
+use ropey::Rope;
+use std::env::current_dir;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::*;
+
+/// an `include directive, with the range of the directive and of the quoted path
+pub struct Include {
+    pub path: String,
+    pub range: Range,
+    pub path_range: Range,
+}
+
+impl LSPServer {
+    pub fn document_link(&self, params: DocumentLinkParams) -> Option<Vec<DocumentLink>> {
+        let uri = params.text_document.uri;
+        debug!("document link: {}", &uri);
+        let file_id = self.srcs.get_id(&uri).to_owned();
+        let file = self.srcs.get_file(file_id)?;
+        let file = file.read().ok()?;
+        let include_dirs = self.srcs.include_dirs.read().ok()?;
+        Some(
+            includes(&file.text)
+                .into_iter()
+                .filter_map(|inc| {
+                    let path = resolve_include(&inc.path, &uri, &include_dirs).ok()?;
+                    Some(DocumentLink {
+                        range: inc.path_range,
+                        target: Url::from_file_path(path).ok(),
+                        tooltip: None,
+                        data: None,
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// goto definition on an `include directive, locates the included file
+    pub fn goto_include(&self, text: &Rope, uri: &Url, pos: Position) -> Option<Location> {
+        let inc = includes(text)
+            .into_iter()
+            .find(|x| x.range.start <= pos && pos <= x.range.end)?;
+        let include_dirs = self.srcs.include_dirs.read().ok()?;
+        let path = resolve_include(&inc.path, uri, &include_dirs).ok()?;
+        Some(Location::new(
+            Url::from_file_path(path).ok()?,
+            Range::default(),
+        ))
+    }
+
+    /// complete the path of an `include directive with the headers, sources and subdirectories
+    /// in the include directories and the directory of the including file
+    pub fn include_completion(
+        &self,
+        text: &Rope,
+        uri: &Url,
+        pos: Position,
+    ) -> Option<CompletionList> {
+        let line = text.line(pos.line as usize);
+        let prefix: String = line
+            .chars()
+            .take(line.utf16_cu_to_char(pos.character as usize))
+            .collect();
+        let partial = include_prefix(&prefix)?;
+        let (subdir, name) = match partial.rfind('/') {
+            Some(idx) => (&partial[..=idx], &partial[idx + 1..]),
+            None => ("", partial),
+        };
+        debug!("include completion: {}", partial);
+        let mut dirs: Vec<PathBuf> = self.srcs.include_dirs.read().ok()?.clone();
+        dirs.extend(
+            uri.to_file_path()
+                .ok()
+                .and_then(|x| x.parent().map(|x| x.to_path_buf())),
+        );
+        // replace the file name being written, as it may not be a word to the client
+        let range = Range::new(
+            Position::new(pos.line, pos.character - name.encode_utf16().count() as u32),
+            pos,
+        );
+        let mut items: Vec<CompletionItem> = Vec::new();
+        for entry in dirs
+            .iter()
+            .filter_map(|x| x.join(subdir).read_dir().ok())
+            .flatten()
+            .flatten()
+        {
+            let path = entry.path();
+            let file_name = match path.file_name().and_then(|x| x.to_str()) {
+                Some(file_name) if !file_name.starts_with('.') => file_name.to_owned(),
+                _ => continue,
+            };
+            let (kind, insert) = if path.is_dir() {
+                (CompletionItemKind::FOLDER, format!("{}/", file_name))
+            } else if matches!(
+                path.extension().and_then(|x| x.to_str()),
+                Some("svh" | "vh" | "sv")
+            ) {
+                (CompletionItemKind::FILE, file_name.clone())
+            } else {
+                continue;
+            };
+            if items.iter().any(|x| x.label == file_name) {
+                continue;
+            }
+            items.push(CompletionItem {
+                label: file_name,
+                kind: Some(kind),
+                detail: Some(path.display().to_string()),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(range, insert))),
+                ..CompletionItem::default()
+            });
+        }
+        items.sort_by(|x, y| x.label.cmp(&y.label));
+        Some(CompletionList {
+            is_incomplete: false,
+            items,
+        })
+    }
+
+    /// diagnostics for `include directives which can't be resolved
+    pub fn include_diagnostics(&self, text: &Rope, uri: &Url) -> Vec<Diagnostic> {
+        let include_dirs = match self.srcs.include_dirs.read() {
+            Ok(include_dirs) => include_dirs,
+            Err(_) => return Vec::new(),
+        };
+        includes(text)
+            .into_iter()
+            .filter_map(|inc| {
+                let searched = resolve_include(&inc.path, uri, &include_dirs).err()?;
+                let dirs: Vec<String> = searched
+                    .iter()
+                    .map(|x| format!("  {}", x.display()))
+                    .collect();
+                Some(Diagnostic::new(
+                    inc.path_range,
+                    Some(DiagnosticSeverity::ERROR),
+                    None,
+                    Some("veridian".to_string()),
+                    format!(
+                        "cannot find include file \"{}\", searched:\n{}",
+                        inc.path,
+                        dirs.join("\n")
+                    ),
+                    None,
+                    None,
+                ))
+            })
+            .collect()
+    }
+}
+
+/// the partial path of the `include directive being written at the end of a line
+fn include_prefix(line: &str) -> Option<&str> {
+    static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r#"`include\s*["<]([^">]*)$"#).unwrap());
+    Some(re.captures(line)?.get(1)?.as_str())
+}
+
+/// find the `include directives in a file
+pub fn includes(text: &Rope) -> Vec<Include> {
+    static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r#"`include\s*(?:"([^"]*)"|<([^>]*)>)"#).unwrap());
+    let mut includes: Vec<Include> = Vec::new();
+    for (line_idx, line) in text.lines().enumerate() {
+        let line = line.to_string();
+        // ignore directives which are commented out
+        for caps in re.captures_iter(strip_comment(&line)) {
+            let (whole, path) = match (caps.get(0), caps.get(1).or_else(|| caps.get(2))) {
+                (Some(whole), Some(path)) => (whole, path),
+                _ => continue,
+            };
+            let pos = |byte_idx: usize| {
+                Position::new(
+                    line_idx as u32,
+                    line[..byte_idx].encode_utf16().count() as u32,
+                )
+            };
+            includes.push(Include {
+                path: path.as_str().to_owned(),
+                range: Range::new(pos(whole.start()), pos(whole.end())),
+                path_range: Range::new(pos(path.start()), pos(path.end())),
+            });
+        }
+    }
+    includes
+}
+
+/// the code of a line before any line comment, a `//` within a string doesn't start a comment
+fn strip_comment(line: &str) -> &str {
+    let mut in_str = false;
+    let mut chars = line.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => in_str = !in_str,
+            '/' if !in_str && matches!(chars.peek(), Some((_, '/'))) => return &line[..idx],
+            _ => (),
+        }
+    }
+    line
+}
+
+/// resolve an included path the same way it's resolved when parsing: relative to the working
+/// directory, then the include directories, then the directory of the including file. Returns
+/// the directories searched if the file can't be found, or the path itself if it's absolute
+pub fn resolve_include(
+    path: &str,
+    uri: &Url,
+    include_dirs: &[PathBuf],
+) -> Result<PathBuf, Vec<PathBuf>> {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        return match path.is_file() {
+            true => Ok(path.to_path_buf()),
+            false => Err(vec![path.to_path_buf()]),
+        };
+    }
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    if let Ok(cwd) = current_dir() {
+        dirs.push(cwd);
+    }
+    dirs.extend(include_dirs.iter().cloned());
+    if let Some(dir) = uri
+        .to_file_path()
+        .ok()
+        .and_then(|x| x.parent().map(|x| x.to_path_buf()))
+    {
+        dirs.push(dir);
+    }
+    dirs.dedup();
+    match dirs.iter().map(|x| x.join(path)).find(|x| x.is_file()) {
+        Some(path) => Ok(path),
+        None => Err(dirs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::test_init;
+    use std::fs::read_to_string;
+
+    #[test]
+    fn test_includes() {
+        test_init();
+        let server = LSPServer::new(None);
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("test_data/top_inc.sv");
+        let uri = Url::from_file_path(&d).unwrap();
+        let mut text = read_to_string(&d).unwrap();
+        text.push_str("`include \"missing.svh\" // `include \"ignored.svh\"\n");
+        text.push_str("`include \"missing//nested.svh\"\n");
+        text.push_str("`include \"/missing/absolute.svh\"\n");
+        let diagnostics = server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.clone(),
+            },
+        });
+        let target = Url::from_file_path(d.with_file_name("simple_bus.svh")).unwrap();
+
+        let links = server
+            .document_link(DocumentLinkParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(
+            links[0].range,
+            Range::new(Position::new(1, 10), Position::new(1, 24))
+        );
+        assert_eq!(links[0].target, Some(target.clone()));
+
+        let location = server.goto_include(&Rope::from_str(&text), &uri, Position::new(1, 3));
+        assert_eq!(location, Some(Location::new(target, Range::default())));
+
+        let missing: Vec<&Diagnostic> = diagnostics
+            .diagnostics
+            .iter()
+            .filter(|x| x.source.as_deref() == Some("veridian"))
+            .collect();
+        assert_eq!(missing.len(), 3);
+        assert!(missing[0].message.contains("missing.svh"));
+        assert!(missing[0]
+            .message
+            .contains(&d.parent().unwrap().display().to_string()));
+        // a path containing a comment marker
+        assert!(missing[1].message.contains("\"missing//nested.svh\""));
+        // an absolute path is the only one searched
+        assert!(missing[2]
+            .message
+            .ends_with("searched:\n  /missing/absolute.svh"));
+    }
+
+    #[test]
+    fn test_include_completion() {
+        test_init();
+        let server = LSPServer::new(None);
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("test_data/top_inc.sv");
+        let uri = Url::from_file_path(&d).unwrap();
+        let complete = |line: &str| {
+            let text = Rope::from_str(&format!("module test;\n{}\n", line));
+            let pos = Position::new(1, line.len() as u32);
+            server.include_completion(&text, &uri, pos).map(|x| x.items)
+        };
+        let items = complete("`include \"").unwrap();
+        let labels: Vec<&str> = items.iter().map(|x| x.label.as_str()).collect();
+        assert!(labels.contains(&"macros.svh"));
+        assert!(labels.contains(&"top.sv"));
+        assert!(!labels.contains(&"verilator_errors.txt"));
+        let diag = items.iter().find(|x| x.label == "diag").unwrap();
+        assert_eq!(diag.kind, Some(CompletionItemKind::FOLDER));
+        assert_eq!(
+            diag.text_edit,
+            Some(CompletionTextEdit::Edit(TextEdit::new(
+                Range::new(Position::new(1, 10), Position::new(1, 10)),
+                "diag/".to_owned()
+            )))
+        );
+        let items = complete("`include \"diag/dia").unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0].text_edit,
+            Some(CompletionTextEdit::Edit(TextEdit::new(
+                Range::new(Position::new(1, 15), Position::new(1, 18)),
+                "diag_test.sv".to_owned()
+            )))
+        );
+        // outside of an include
+        assert_eq!(complete("`include \"macros.svh\""), None);
+        assert_eq!(complete("string s = \""), None);
+    }
+}
@@ -1,9 +1,15 @@
 #![recursion_limit = "256"]
 
+pub mod call_hierarchy;
+pub mod code_lens;
 pub mod completion;
 pub mod definition;
 pub mod diagnostics;
 pub mod format;
+pub mod hierarchy;
+pub mod include;
 pub mod server;
+pub mod signature_help;
 pub mod sources;
 pub mod support;
+pub mod type_hierarchy;
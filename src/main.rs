@@ -1,18 +1,23 @@
 #![recursion_limit = "256"]
 
 use log::info;
-use std::sync::Arc;
 use structopt::StructOpt;
 use tower_lsp::{LspService, Server};
 
+mod call_hierarchy;
+mod code_lens;
 mod completion;
 mod definition;
 mod diagnostics;
 mod format;
+mod hierarchy;
+mod include;
 mod server;
+mod signature_help;
 mod sources;
 #[cfg(test)]
 mod support;
+mod type_hierarchy;
 use server::Backend;
 
 #[derive(StructOpt, Debug)]
@@ -30,6 +35,8 @@ async fn main() {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, messages) = LspService::new(|client| Arc::new(Backend::new(client, log_handle)));
+    let (service, messages) = LspService::build(|client| Backend::new(client, log_handle))
+        .custom_method("veridian/hierarchy", Backend::hierarchy)
+        .finish();
     Server::new(stdin, stdout, messages).serve(service).await;
 }
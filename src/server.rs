@@ -1,6 +1,7 @@
 use crate::sources::*;
 
 use crate::completion::keyword::*;
+use crate::hierarchy::{HierarchyNode, HierarchyParams};
 use flexi_logger::LoggerHandle;
 use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
@@ -8,8 +9,13 @@ use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
 use std::string::ToString;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Mutex, RwLock};
 use tower_lsp::jsonrpc::{Error, ErrorCode, Result};
+use tower_lsp::lsp_types::request::{
+    GotoDeclarationParams, GotoDeclarationResponse, GotoImplementationParams,
+    GotoImplementationResponse, GotoTypeDefinitionParams, GotoTypeDefinitionResponse,
+};
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer};
 use which::which;
@@ -21,6 +27,8 @@ pub struct LSPServer {
     pub directives: Vec<CompletionItem>,
     pub conf: RwLock<ProjectConfig>,
     pub log_handle: Mutex<Option<LoggerHandle>>,
+    // whether the client accepts LocationLinks in response to goto definition
+    pub definition_link_support: AtomicBool,
 }
 
 impl LSPServer {
@@ -28,10 +36,11 @@ impl LSPServer {
         LSPServer {
             srcs: Sources::new(),
             key_comps: keyword_completions(KEYWORDS),
-            sys_tasks: other_completions(SYS_TASKS),
+            sys_tasks: sys_task_completions(SYS_TASKS),
             directives: other_completions(DIRECTIVES),
             conf: RwLock::new(ProjectConfig::default()),
             log_handle: Mutex::new(log_handle),
+            definition_link_support: AtomicBool::new(false),
         }
     }
 }
@@ -39,6 +48,9 @@ impl LSPServer {
 pub struct Backend {
     client: Client,
     server: LSPServer,
+    // type hierarchy isn't part of the server capabilities in this version of lsp-types, so it
+    // is registered dynamically if the client supports it
+    register_type_hierarchy: AtomicBool,
 }
 
 impl Backend {
@@ -46,8 +58,14 @@ impl Backend {
         Backend {
             client,
             server: LSPServer::new(Some(log_handle)),
+            register_type_hierarchy: AtomicBool::new(false),
         }
     }
+
+    /// handler for the veridian/hierarchy custom request
+    pub async fn hierarchy(&self, params: HierarchyParams) -> Result<Vec<HierarchyNode>> {
+        Ok(self.server.hierarchy(params))
+    }
 }
 
 #[derive(strum_macros::Display, Debug, Serialize, Deserialize)]
@@ -73,6 +91,11 @@ pub struct ProjectConfig {
     pub verilator: Verilator,
     // log level
     pub log_level: LogLevel,
+    // top module of the design hierarchy, defaults to modules which are never instantiated
+    pub top_module: Option<String>,
+    // command run when a code lens is clicked, given the uri and position of the definition and
+    // the locations it counts. Without one, code lenses only show the count
+    pub code_lens_command: Option<String>,
 
     pub project_path: PathBuf
 }
@@ -83,6 +106,8 @@ impl Default for ProjectConfig {
             verible_lint: VeribleLint::default(),
             verilator: Verilator::default(),
             log_level: LogLevel::Info,
+            top_module: None,
+            code_lens_command: None,
             project_path: PathBuf::new()
         }
     }
@@ -173,6 +198,24 @@ fn read_config(root_uri: Option<Url>) -> anyhow::Result<ProjectConfig> {
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
     async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let type_hierarchy = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|x| x.type_hierarchy.as_ref());
+        self.register_type_hierarchy.store(
+            type_hierarchy.and_then(|x| x.dynamic_registration) == Some(true),
+            Ordering::Relaxed,
+        );
+        let definition = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|x| x.definition.as_ref());
+        self.server.definition_link_support.store(
+            definition.and_then(|x| x.link_support) == Some(true),
+            Ordering::Relaxed,
+        );
         // grab include dirs and source dirs from config, and convert to abs path
         match read_config(params.root_uri) {
             Ok(conf) => {
@@ -229,11 +272,14 @@ impl LanguageServer for Backend {
                     },
                 )),
                 completion_provider: Some(CompletionOptions {
-                    resolve_provider: Some(false),
+                    resolve_provider: Some(true),
                     trigger_characters: Some(vec![
                         ".".to_string(),
                         "$".to_string(),
                         "`".to_string(),
+                        ":".to_string(),
+                        "\"".to_string(),
+                        "/".to_string(),
                     ]),
                     work_done_progress_options: WorkDoneProgressOptions {
                         work_done_progress: None,
@@ -243,14 +289,47 @@ impl LanguageServer for Backend {
                     completion_item: None,
                 }),
                 definition_provider: Some(OneOf::Left(true)),
+                declaration_provider: Some(DeclarationCapability::Simple(true)),
+                type_definition_provider: Some(TypeDefinitionProviderCapability::Simple(true)),
+                implementation_provider: Some(ImplementationProviderCapability::Simple(true)),
+                call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 document_symbol_provider: Some(OneOf::Left(true)),
                 document_highlight_provider: Some(OneOf::Left(true)),
+                signature_help_provider: Some(SignatureHelpOptions {
+                    trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+                    retrigger_characters: None,
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                }),
+                code_lens_provider: Some(CodeLensOptions {
+                    resolve_provider: Some(false),
+                }),
+                document_link_provider: Some(DocumentLinkOptions {
+                    resolve_provider: Some(false),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                }),
                 ..ServerCapabilities::default()
             },
         })
     }
     async fn initialized(&self, _: InitializedParams) {
+        if self.register_type_hierarchy.load(Ordering::Relaxed) {
+            let options = TypeHierarchyRegistrationOptions {
+                text_document_registration_options: TextDocumentRegistrationOptions {
+                    document_selector: None,
+                },
+                type_hierarchy_options: TypeHierarchyOptions::default(),
+                static_registration_options: StaticRegistrationOptions::default(),
+            };
+            let registration = Registration {
+                id: "veridian-type-hierarchy".to_string(),
+                method: "textDocument/prepareTypeHierarchy".to_string(),
+                register_options: serde_json::to_value(options).ok(),
+            };
+            if let Err(e) = self.client.register_capability(vec![registration]).await {
+                warn!("failed to register type hierarchy: {}", e);
+            }
+        }
         self.client
             .log_message(MessageType::INFO, "veridian initialized!")
             .await;
@@ -284,12 +363,69 @@ impl LanguageServer for Backend {
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
         Ok(self.server.completion(params))
     }
+    async fn completion_resolve(&self, params: CompletionItem) -> Result<CompletionItem> {
+        Ok(self.server.completion_resolve(params))
+    }
     async fn goto_definition(
         &self,
         params: GotoDefinitionParams,
     ) -> Result<Option<GotoDefinitionResponse>> {
         Ok(self.server.goto_definition(params))
     }
+    async fn goto_declaration(
+        &self,
+        params: GotoDeclarationParams,
+    ) -> Result<Option<GotoDeclarationResponse>> {
+        Ok(self.server.goto_declaration(params))
+    }
+    async fn goto_type_definition(
+        &self,
+        params: GotoTypeDefinitionParams,
+    ) -> Result<Option<GotoTypeDefinitionResponse>> {
+        Ok(self.server.goto_type_definition(params))
+    }
+    async fn goto_implementation(
+        &self,
+        params: GotoImplementationParams,
+    ) -> Result<Option<GotoImplementationResponse>> {
+        Ok(self.server.goto_implementation(params))
+    }
+    async fn prepare_call_hierarchy(
+        &self,
+        params: CallHierarchyPrepareParams,
+    ) -> Result<Option<Vec<CallHierarchyItem>>> {
+        Ok(self.server.prepare_call_hierarchy(params))
+    }
+    async fn incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        Ok(self.server.incoming_calls(params))
+    }
+    async fn outgoing_calls(
+        &self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+        Ok(self.server.outgoing_calls(params))
+    }
+    async fn prepare_type_hierarchy(
+        &self,
+        params: TypeHierarchyPrepareParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        Ok(self.server.prepare_type_hierarchy(params))
+    }
+    async fn supertypes(
+        &self,
+        params: TypeHierarchySupertypesParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        Ok(self.server.supertypes(params))
+    }
+    async fn subtypes(
+        &self,
+        params: TypeHierarchySubtypesParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        Ok(self.server.subtypes(params))
+    }
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
         Ok(self.server.hover(params))
     }
@@ -314,6 +450,18 @@ impl LanguageServer for Backend {
     ) -> Result<Option<Vec<DocumentHighlight>>> {
         Ok(self.server.document_highlight(params))
     }
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
+        Ok(self.server.signature_help(params))
+    }
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        Ok(self.server.code_lens(params))
+    }
+    async fn document_link(
+        &self,
+        params: DocumentLinkParams,
+    ) -> Result<Option<Vec<DocumentLink>>> {
+        Ok(self.server.document_link(params))
+    }
 }
 
 #[cfg(test)]
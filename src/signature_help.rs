@@ -0,0 +1,475 @@
+use crate::completion::keyword::SYS_TASKS;
+use crate::completion::{name_path, trailing_name};
+use crate::definition::const_eval::module_params;
+use crate::definition::def_types::*;
+use crate::server::LSPServer;
+use crate::sources::LSPSupport;
+use log::debug;
+use ropey::Rope;
+use tower_lsp::lsp_types::*;
+
+// limit on how many lines back to look for the start of a call
+const MAX_CALL_LINES: usize = 64;
+
+impl LSPServer {
+    pub fn signature_help(&self, params: SignatureHelpParams) -> Option<SignatureHelp> {
+        let doc = params.text_document_position_params;
+        let uri = doc.text_document.uri;
+        debug!("signature help: {}", &uri);
+        let file_id = self.srcs.get_id(&uri).to_owned();
+        self.srcs.wait_parse_ready(file_id, false);
+        let file = self.srcs.get_file(file_id)?;
+        let file = file.read().ok()?;
+        let call = call_at(&file.text, doc.position)?;
+        debug!("signature help for: {:?}", &call);
+        let byte_idx = file.text.pos_to_byte(&doc.position);
+
+        // system tasks and functions
+        if let Some(name) = call.path[0].strip_prefix('$') {
+            let task = SYS_TASKS.iter().find(|x| x.name == name)?;
+            let signature = signature_information(
+                task.signature(),
+                task.args,
+                Some(Documentation::String(task.doc.to_owned())),
+            );
+            return Some(SignatureHelp {
+                active_parameter: Some(active_parameter(task.args, call.arg)),
+                signatures: vec![signature],
+                active_signature: Some(0),
+            });
+        }
+
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let scope_tree = scope_tree.as_ref()?;
+        let (head, args) = match call.params {
+            // parameter overrides of a module instantiation
+            true => {
+                let module = scope_tree
+                    .scopes()
+                    .iter()
+                    .find(|x| x.ident() == call.path[0] && is_design_unit(x.as_ref()))?;
+                (
+                    format!("{} #(", module.ident()),
+                    module_params(module.as_ref()),
+                )
+            }
+            false => {
+                let subroutine = callee(scope_tree, &call, byte_idx, &uri)?;
+                let keyword = clean_type_str(&subroutine.type_str(), &subroutine.ident());
+                let ports: Vec<&dyn Definition> = subroutine
+                    .defs()
+                    .iter()
+                    .filter(|x| x.def_type() == DefinitionType::Port)
+                    .map(|x| x.as_ref())
+                    .collect();
+                (format!("{} {}(", keyword, subroutine.ident()), ports)
+            }
+        };
+        let active = match &call.named {
+            Some(named) => args.iter().position(|x| &x.ident() == named),
+            None => Some(call.arg),
+        };
+        let args: Vec<String> = args.iter().map(|x| arg_text(*x)).collect();
+        let label = format!("{}{})", head, args.join(", "));
+        let args: Vec<&str> = args.iter().map(|x| x.as_str()).collect();
+        Some(SignatureHelp {
+            signatures: vec![signature_information(label, &args, None)],
+            active_signature: Some(0),
+            active_parameter: active.map(|x| x as u32),
+        })
+    }
+}
+
+/// the function or task being called, a constructor is found through the type of the variable
+/// being assigned, or the base class for `super.new`
+fn callee<'a>(
+    scope_tree: &'a GenericScope,
+    call: &Call,
+    byte_idx: usize,
+    uri: &Url,
+) -> Option<&'a dyn Scope> {
+    let class = match call.path.iter().map(|x| x.as_str()).collect::<Vec<&str>>()[..] {
+        ["new"] => {
+            let lhs = call.prefix.trim_end().strip_suffix('=')?.trim_end();
+            let var = scope_tree.find_path(&name_path(trailing_name(lhs))?, byte_idx, uri)?;
+            scope_tree.find_type(var)?.as_scope()?
+        }
+        ["super", "new"] => scope_tree.base_class(scope_tree.enclosing_class(byte_idx, uri)?)?,
+        _ => {
+            let def = scope_tree.find_path(&call.path, byte_idx, uri)?;
+            return match def.def_type() {
+                DefinitionType::Subroutine => def.as_scope(),
+                _ => None,
+            };
+        }
+    };
+    match class.child("new") {
+        Some(new) => new.as_scope(),
+        None => scope_tree
+            .method_bodies(&class.ident(), "new")
+            .first()?
+            .as_scope(),
+    }
+}
+
+/// the text of an argument or parameter declaration, with its default value
+fn arg_text(def: &dyn Definition) -> String {
+    let type_str = def.type_str();
+    let text = match type_str.trim().is_empty() {
+        true => def.ident(),
+        false => type_str,
+    };
+    text.split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .replace("[ ", "[")
+        .replace(" ]", "]")
+        .replace(" : ", ":")
+}
+
+/// a signature with the range of each of its arguments within the label
+fn signature_information(
+    label: String,
+    args: &[&str],
+    documentation: Option<Documentation>,
+) -> SignatureInformation {
+    let mut parameters: Vec<ParameterInformation> = Vec::new();
+    let mut offset = label.find('(').unwrap_or(label.len());
+    for arg in args {
+        let start = match label[offset..].find(arg) {
+            Some(start) => offset + start,
+            None => break,
+        };
+        offset = start + arg.len();
+        let utf16_len = |x: &str| x.encode_utf16().count() as u32;
+        parameters.push(ParameterInformation {
+            label: ParameterLabel::LabelOffsets([
+                utf16_len(&label[..start]),
+                utf16_len(&label[..offset]),
+            ]),
+            documentation: None,
+        });
+    }
+    SignatureInformation {
+        label,
+        documentation,
+        parameters: Some(parameters),
+        active_parameter: None,
+    }
+}
+
+/// the argument being written, the trailing arguments taken by a variable argument list are
+/// all the last argument
+fn active_parameter(args: &[&str], arg: usize) -> u32 {
+    match args.last() {
+        Some(last) if last.ends_with("...") && arg >= args.len() => (args.len() - 1) as u32,
+        _ => arg as u32,
+    }
+}
+
+/// a call being written
+#[derive(Debug)]
+struct Call {
+    // the name of the function, task or module, as a path such as [pkt, size] or [pkg, ::, f]
+    path: Vec<String>,
+    // the text before the name, ex. the variable assigned the result
+    prefix: String,
+    // whether these are the parameter overrides of a module instantiation
+    params: bool,
+    // the index of the argument being written
+    arg: usize,
+    // the name of the argument being written with a named binding, ex. b in `f(.b(`
+    named: Option<String>,
+}
+
+/// the call at a position, ex. `$display` with the argument 1 in `$display("%d", |`
+fn call_at(text: &Rope, pos: Position) -> Option<Call> {
+    // start from the line after the previous statement
+    let line_idx = pos.line as usize;
+    let mut start_line = line_idx;
+    while start_line > 0 && line_idx - start_line < MAX_CALL_LINES {
+        let line = text.line(start_line - 1).to_string();
+        let code = line.split("//").next().unwrap_or_default().trim_end();
+        if code.ends_with(';') {
+            break;
+        }
+        start_line -= 1;
+    }
+    let end = text.pos_to_char(&pos);
+    let segment: String = text.slice(text.line_to_char(start_line)..end).to_string();
+
+    // the open brackets, with the call of an open paren and the commas within
+    let mut open: Vec<(char, Option<Call>)> = Vec::new();
+    let mut code = String::new();
+    let mut chars = segment.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                // skip over strings, including escaped quotes
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => (),
+                    }
+                }
+                code.push_str("\"\"");
+                continue;
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                code.push('\n');
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+                code.push(' ');
+                continue;
+            }
+            '(' => open.push(('(', call_before(&code))),
+            '[' | '{' => open.push((c, None)),
+            ')' | ']' | '}' => {
+                open.pop();
+            }
+            ',' => {
+                if let Some((_, Some(call))) = open.last_mut() {
+                    call.arg += 1;
+                }
+            }
+            _ => (),
+        }
+        code.push(c);
+    }
+    // the innermost call, any brackets within it are part of the argument being written
+    let mut named: Option<String> = None;
+    for (_, call) in open.into_iter().rev() {
+        let mut call = match call {
+            Some(call) => call,
+            None => continue,
+        };
+        // a named argument binding, ex. `.b(`
+        if call.path[0].is_empty() {
+            named = named.or_else(|| call.path.get(1).cloned());
+            continue;
+        }
+        call.named = named;
+        return Some(call);
+    }
+    None
+}
+
+/// the call opened by a paren at the end of some code, a named argument binding is given as a
+/// path starting with an empty identifier
+fn call_before(code: &str) -> Option<Call> {
+    let mut before = code.trim_end();
+    let params = before.ends_with('#');
+    if params {
+        before = before[..before.len() - 1].trim_end();
+    }
+    let is_name = |c: char| c.is_alphanumeric() || c == '_' || c == '$' || c == '.' || c == ':';
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_name(*c))
+        .last()
+        .map_or(before.len(), |(i, _)| i);
+    let name = &before[start..];
+    let prefix = &before[..start];
+    let mut path: Vec<String> = Vec::new();
+    for (i, part) in name.split("::").enumerate() {
+        if i > 0 {
+            path.push("::".to_owned());
+        }
+        path.extend(part.split('.').map(|x| x.to_owned()));
+    }
+    let bound = prefix.trim_end().ends_with(['(', ',']);
+    let valid = |x: &String| x == "::" || x.starts_with(|c: char| c.is_alphabetic() || c == '_');
+    let valid = match path.split_first() {
+        // a named argument binding
+        Some((first, rest)) if first.is_empty() => bound && rest.len() == 1 && valid(&rest[0]),
+        Some((first, rest)) if first.starts_with('$') => rest.is_empty(),
+        _ => path.iter().all(valid),
+    };
+    if !valid {
+        return None;
+    }
+    Some(Call {
+        path,
+        prefix: prefix.to_owned(),
+        params,
+        arg: 0,
+        named: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::test_init;
+
+    #[test]
+    fn test_sys_task_signature_help() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let text = r#"module test;
+  initial begin
+    $display("%d, %d", {a, b}, (c + d),
+    x = $clog2(
+    y = $urandom_range(10, f(1, 2)
+  end
+endmodule
+"#;
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+        let help = |line, character| {
+            server
+                .signature_help(SignatureHelpParams {
+                    context: None,
+                    text_document_position_params: TextDocumentPositionParams {
+                        text_document: TextDocumentIdentifier { uri: uri.clone() },
+                        position: Position::new(line, character),
+                    },
+                    work_done_progress_params: WorkDoneProgressParams::default(),
+                })
+                .map(|x| (x.signatures[0].label.clone(), x.active_parameter.unwrap()))
+        };
+        assert_eq!(
+            help(2, 39),
+            Some(("$display([format], args...)".to_owned(), 1))
+        );
+        // a concatenation within an argument
+        assert_eq!(
+            help(2, 25),
+            Some(("$display([format], args...)".to_owned(), 1))
+        );
+        assert_eq!(help(3, 15), Some(("integer $clog2(n)".to_owned(), 0)));
+        assert_eq!(
+            help(4, 27),
+            Some((
+                "int unsigned $urandom_range(maxval, [minval])".to_owned(),
+                1
+            ))
+        );
+        // a call to a user function
+        assert_eq!(help(4, 33), None);
+
+        let help = server
+            .signature_help(SignatureHelpParams {
+                context: None,
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position: Position::new(3, 15),
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            })
+            .unwrap();
+        assert_eq!(
+            help.signatures[0].parameters.as_ref().unwrap()[0].label,
+            ParameterLabel::LabelOffsets([15, 16])
+        );
+    }
+
+    #[test]
+    fn test_signature_help() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///defs.sv").unwrap();
+        let uri2 = Url::parse("file:///top.sv").unwrap();
+        let text = r#"package pkg;
+  function automatic int add(input int a, int b = 1);
+    return a + b;
+  endfunction
+endpackage
+class packet;
+  function new(int id, string name = "pkt");
+  endfunction
+  task send(output bit ok);
+  endtask
+endclass
+class big_packet extends packet;
+  function new();
+    super.new(
+  endfunction
+endclass
+module adder #(
+  parameter int WIDTH = 8,
+  parameter bit SIGNED = 0
+) ();
+endmodule
+"#;
+        let text2 = r#"module top;
+  packet pkt;
+  initial begin
+    x = pkg::add(1, 
+    pkt = new(.name(
+    pkt.send(
+  end
+  adder #(.SIGNED(1), .WIDTH(
+endmodule
+"#;
+        for (uri, text) in &[(&uri, text), (&uri2, text2)] {
+            server.did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: (*uri).clone(),
+                    language_id: "systemverilog".to_owned(),
+                    version: 0,
+                    text: text.to_string(),
+                },
+            });
+            let fid = server.srcs.get_id(uri);
+            server.srcs.wait_parse_ready(fid, true);
+        }
+        let help = |uri: &Url, line, character| {
+            server
+                .signature_help(SignatureHelpParams {
+                    context: None,
+                    text_document_position_params: TextDocumentPositionParams {
+                        text_document: TextDocumentIdentifier { uri: uri.clone() },
+                        position: Position::new(line, character),
+                    },
+                    work_done_progress_params: WorkDoneProgressParams::default(),
+                })
+                .map(|x| (x.signatures[0].label.clone(), x.active_parameter))
+        };
+        let signature = |label: &str, active| Some((label.to_owned(), Some(active)));
+        assert_eq!(
+            help(&uri2, 3, 20),
+            signature("function automatic int add(input int a, int b = 1)", 1)
+        );
+        assert_eq!(
+            help(&uri2, 4, 20),
+            signature("function new(int id, string name = \"pkt\")", 1)
+        );
+        assert_eq!(help(&uri2, 5, 13), signature("task send(output bit ok)", 0));
+        assert_eq!(
+            help(&uri2, 7, 29),
+            signature(
+                "adder #(parameter int WIDTH = 8, parameter bit SIGNED = 0)",
+                0
+            )
+        );
+        assert_eq!(
+            help(&uri, 13, 14),
+            signature("function new(int id, string name = \"pkt\")", 0)
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::definition::def_types::*;
-use crate::definition::get_scopes;
+use crate::definition::{get_scopes, text_macro_defs};
 use crate::diagnostics::{get_diagnostics, is_hidden};
 use crate::server::LSPServer;
 use log::{debug, error, trace};
@@ -42,7 +42,12 @@ impl LSPServer {
         let file_id = self.srcs.get_id(&uri);
         let file = self.srcs.get_file(file_id).unwrap();
         let file = file.read().unwrap();
-        get_diagnostics(uri, &file.text, urls, &self.conf.read().unwrap())
+        let mut diagnostics =
+            get_diagnostics(uri.clone(), &file.text, urls, &self.conf.read().unwrap());
+        diagnostics
+            .diagnostics
+            .extend(self.include_diagnostics(&file.text, &uri));
+        diagnostics
     }
 
     pub fn did_change(&self, params: DidChangeTextDocumentParams) {
@@ -75,12 +80,16 @@ impl LSPServer {
         let file_id = self.srcs.get_id(&params.text_document.uri);
         let file = self.srcs.get_file(file_id).unwrap();
         let file = file.read().unwrap();
-        get_diagnostics(
-            params.text_document.uri,
+        let mut diagnostics = get_diagnostics(
+            params.text_document.uri.clone(),
             &file.text,
             urls,
             &self.conf.read().unwrap(),
-        )
+        );
+        diagnostics
+            .diagnostics
+            .extend(self.include_diagnostics(&file.text, &params.text_document.uri));
+        diagnostics
     }
 }
 
@@ -220,6 +229,8 @@ impl Sources {
                     Some(tree) => get_scopes(tree, uri),
                     None => None,
                 };
+                // macros are found in the text, so they're indexed even if the parse fails
+                let text_macros = text_macro_defs(&text, uri);
                 trace!(
                     "{}, parse read complete: {}",
                     uri,
@@ -231,16 +242,25 @@ impl Sources {
                 drop(file);
                 debug!("try write global scope");
                 let mut global_scope = scope_handle.write().unwrap();
-                match &mut *global_scope {
-                    Some(scope) => {
-                        if let Some(tree) = &mut scope_tree {
-                            scope.defs.retain(|x| &x.url() != uri);
-                            scope.scopes.retain(|x| &x.url() != uri);
-                            scope.defs.append(&mut tree.defs);
-                            scope.scopes.append(&mut tree.scopes);
-                        }
+                let scope = global_scope.get_or_insert_with(|| {
+                    let mut scope = GenericScope::new(uri);
+                    scope.ident = "global".to_string();
+                    scope
+                });
+                match &mut scope_tree {
+                    Some(tree) => {
+                        scope.defs.retain(|x| &x.url() != uri);
+                        scope.scopes.retain(|x| &x.url() != uri);
+                        scope.defs.append(&mut tree.defs);
+                        scope.scopes.append(&mut tree.scopes);
                     }
-                    None => *global_scope = scope_tree,
+                    // keep the definitions from the last successful parse, except for macros
+                    None => scope
+                        .defs
+                        .retain(|x| &x.url() != uri || x.def_type() != DefinitionType::Macro),
+                }
+                for text_macro in text_macros {
+                    scope.defs.push(Box::new(text_macro));
                 }
                 // eprintln!("{:#?}", *global_scope);
                 drop(global_scope);
@@ -315,6 +335,48 @@ impl Sources {
         *self.names.read().unwrap().get(uri).unwrap()
     }
 
+    /// get the text of the file with the given url
+    pub fn get_text(&self, uri: &Url) -> Option<Rope> {
+        let id = *self.names.read().ok()?.get(uri)?;
+        let file = self.get_file(id)?;
+        let file = file.read().ok()?;
+        Some(file.text.clone())
+    }
+
+    /// convert a byte index in the file with the given url to a position
+    pub fn byte_to_pos(&self, uri: &Url, byte_idx: usize) -> Option<Position> {
+        let id = *self.names.read().ok()?.get(uri)?;
+        let file = self.get_file(id)?;
+        let file = file.read().ok()?;
+        Some(file.text.byte_to_pos(byte_idx))
+    }
+
+    /// the range of a definition, covering the whole scope for a scope, and the range of its
+    /// identifier
+    pub fn def_ranges(&self, def: &dyn Definition) -> Option<(Range, Range)> {
+        let url = def.url();
+        let ident_range = Range::new(
+            self.byte_to_pos(&url, def.byte_idx())?,
+            self.byte_to_pos(&url, def.byte_idx() + def.ident().len())?,
+        );
+        let range = match def.as_scope() {
+            Some(scope) => Range::new(
+                self.byte_to_pos(&url, scope.start())?,
+                self.byte_to_pos(&url, scope.end())?,
+            ),
+            None => ident_range,
+        };
+        Some((range, ident_range))
+    }
+
+    /// convert a position in the file with the given url to a byte index
+    pub fn pos_to_byte(&self, uri: &Url, pos: &Position) -> Option<usize> {
+        let id = *self.names.read().ok()?.get(uri)?;
+        let file = self.get_file(id)?;
+        let file = file.read().ok()?;
+        Some(file.text.pos_to_byte(pos))
+    }
+
     /// compute identifier completions
     pub fn get_completions(
         &self,
@@ -330,7 +392,7 @@ impl Sources {
                 .read()
                 .ok()?
                 .as_ref()?
-                .get_completion(token, byte_idx, url),
+                .get_completion(token, byte_idx, url, 0),
         })
     }
 
@@ -343,13 +405,48 @@ impl Sources {
     ) -> Option<CompletionList> {
         debug!("retrieving dot completion for token: {}", &token);
         let tree = self.scope_tree.read().ok()?;
+        let tree = tree.as_ref()?;
+        let path: Vec<String> = token.split('.').map(|x| x.to_owned()).collect();
+        let mut items = Vec::new();
+        if path.len() == 1 {
+            items = tree.get_dot_completion(token, byte_idx, url, tree);
+        }
+        // resolve hierarchical names and struct members through their types
+        if items.is_empty() {
+            items = tree.get_member_completion(&path, byte_idx, url);
+        }
+        Some(CompletionList {
+            is_incomplete: false,
+            items,
+        })
+    }
+
+    /// compute completions for the members of a package or class, after `pkg::`
+    pub fn get_scoped_completions(&self, path: &[String], token: &str) -> Option<CompletionList> {
+        debug!("retrieving scoped completion for: {:?}", path);
+        let tree = self.scope_tree.read().ok()?;
         Some(CompletionList {
             is_incomplete: false,
             items: tree
                 .as_ref()?
-                .get_dot_completion(token, byte_idx, url, tree.as_ref()?),
+                .get_scoped_completion(path)
+                .into_iter()
+                .filter(|x| x.label.starts_with(token))
+                .collect(),
         })
     }
+
+    /// compute completions for the literals of the enum type of the given hierarchical name
+    pub fn get_enum_completions(
+        &self,
+        path: &[String],
+        byte_idx: usize,
+        url: &Url,
+    ) -> Option<Vec<CompletionItem>> {
+        debug!("retrieving enum completion for: {:?}", path);
+        let tree = self.scope_tree.read().ok()?;
+        Some(tree.as_ref()?.get_enum_completion(path, byte_idx, url))
+    }
 }
 
 //TODO: show all unrecoverable parse errors to user
@@ -444,7 +541,6 @@ pub trait LSPSupport {
     fn byte_to_pos(&self, byte_idx: usize) -> Position;
     fn char_to_pos(&self, char_idx: usize) -> Position;
     fn range_to_char_range(&self, range: &Range) -> StdRange<usize>;
-    fn char_range_to_range(&self, range: StdRange<usize>) -> Range;
     fn apply_change(&mut self, change: &TextDocumentContentChangeEvent);
 }
 
@@ -471,12 +567,6 @@ impl LSPSupport for Rope {
     fn range_to_char_range(&self, range: &Range) -> StdRange<usize> {
         self.pos_to_char(&range.start)..self.pos_to_char(&range.end)
     }
-    fn char_range_to_range(&self, range: StdRange<usize>) -> Range {
-        Range {
-            start: self.char_to_pos(range.start),
-            end: self.char_to_pos(range.end),
-        }
-    }
     fn apply_change(&mut self, change: &TextDocumentContentChangeEvent) {
         if let Some(range) = change.range {
             let char_range = self.range_to_char_range(&range);
@@ -510,12 +600,6 @@ impl LSPSupport for RopeSlice<'_> {
     fn range_to_char_range(&self, range: &Range) -> StdRange<usize> {
         self.pos_to_char(&range.start)..self.pos_to_char(&range.end)
     }
-    fn char_range_to_range(&self, range: StdRange<usize>) -> Range {
-        Range {
-            start: self.char_to_pos(range.start),
-            end: self.char_to_pos(range.end),
-        }
-    }
     fn apply_change(&mut self, _: &TextDocumentContentChangeEvent) {
         panic!("can't edit a rope slice");
     }
@@ -612,6 +696,36 @@ endmodule"#;
             .contains_scope("test"));
     }
 
+    #[test]
+    fn test_macros_without_parse() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let text = "`define DEPTH 16\n`define MAX(a, b) ((a) > (b) ? (a) : (b))\n`include \"missing.svh\"\n";
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+        let fid = server.srcs.get_id(&uri);
+        server.srcs.wait_parse_ready(fid, true);
+        let file = server.srcs.get_file(fid).unwrap();
+        assert!(file.read().unwrap().syntax_tree.is_none());
+        let scope_tree = server.srcs.scope_tree.read().unwrap();
+        let macros: Vec<String> = scope_tree
+            .as_ref()
+            .unwrap()
+            .defs()
+            .iter()
+            .filter(|x| x.def_type() == DefinitionType::Macro)
+            .map(|x| x.ident())
+            .collect();
+        assert_eq!(macros, vec!["DEPTH", "MAX"]);
+    }
+
     #[test]
     fn test_header() {
         test_init();
@@ -0,0 +1,174 @@
+use crate::definition::def_types::*;
+use crate::definition::get_definition_path;
+use crate::server::LSPServer;
+use crate::sources::LSPSupport;
+use log::debug;
+use tower_lsp::lsp_types::*;
+
+impl LSPServer {
+    pub fn prepare_type_hierarchy(
+        &self,
+        params: TypeHierarchyPrepareParams,
+    ) -> Option<Vec<TypeHierarchyItem>> {
+        let doc = params.text_document_position_params.text_document.uri;
+        let pos = params.text_document_position_params.position;
+        let file_id = self.srcs.get_id(&doc).to_owned();
+        self.srcs.wait_parse_ready(file_id, false);
+        let file = self.srcs.get_file(file_id)?;
+        let file = file.read().ok()?;
+        let path = get_definition_path(file.text.line(pos.line as usize), pos);
+        debug!("prepare type hierarchy, path: {:?}", &path);
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let scope_tree = scope_tree.as_ref()?;
+        let def = scope_tree.find_path(&path, file.text.pos_to_byte(&pos), &doc)?;
+        // for a class handle, use the class of the handle
+        let class = match def.def_type() {
+            DefinitionType::Class => def,
+            _ => scope_tree.find_type(def)?,
+        };
+        if class.def_type() != DefinitionType::Class {
+            return None;
+        }
+        Some(vec![self.type_item(class)?])
+    }
+
+    pub fn supertypes(
+        &self,
+        params: TypeHierarchySupertypesParams,
+    ) -> Option<Vec<TypeHierarchyItem>> {
+        debug!("supertypes: {}", &params.item.name);
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let scope_tree = scope_tree.as_ref()?;
+        let class = self.item_class(scope_tree, &params.item)?;
+        Some(
+            scope_tree
+                .superclasses(class)
+                .into_iter()
+                .filter_map(|x| self.type_item(x))
+                .collect(),
+        )
+    }
+
+    pub fn subtypes(&self, params: TypeHierarchySubtypesParams) -> Option<Vec<TypeHierarchyItem>> {
+        debug!("subtypes: {}", &params.item.name);
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let scope_tree = scope_tree.as_ref()?;
+        let class = self.item_class(scope_tree, &params.item)?;
+        Some(
+            scope_tree
+                .subclasses(class)
+                .into_iter()
+                .filter_map(|x| self.type_item(x))
+                .collect(),
+        )
+    }
+
+    /// find the class a type hierarchy item was created from
+    fn item_class<'a>(
+        &self,
+        scope_tree: &'a GenericScope,
+        item: &TypeHierarchyItem,
+    ) -> Option<&'a dyn Scope> {
+        let byte_idx = self
+            .srcs
+            .pos_to_byte(&item.uri, &item.selection_range.start)?;
+        scope_tree.scope_at(&item.uri, byte_idx)
+    }
+
+    fn type_item(&self, def: &dyn Definition) -> Option<TypeHierarchyItem> {
+        let (range, selection_range) = self.srcs.def_ranges(def)?;
+        Some(TypeHierarchyItem {
+            name: def.ident(),
+            kind: def.symbol_kind(),
+            tags: None,
+            detail: Some(clean_type_str(&def.type_str(), &def.ident())),
+            uri: def.url(),
+            range,
+            selection_range,
+            data: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::test_init;
+
+    #[test]
+    fn test_type_hierarchy() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let text = r#"package base_pkg;
+  interface class resettable;
+    pure virtual function void reset();
+  endclass
+  virtual class component;
+    int id;
+    function string get_name();
+    endfunction
+  endclass
+endpackage
+class driver extends base_pkg::component implements base_pkg::resettable;
+  virtual function void reset();
+  endfunction
+endclass
+class monitor extends base_pkg::component;
+endclass
+module test;
+  driver drv;
+  initial drv.id = drv.get_name();
+endmodule
+package other_pkg;
+  class component;
+  endclass
+  class logger extends component;
+  endclass
+endpackage
+"#;
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "systemverilog".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+        let fid = server.srcs.get_id(&uri);
+        server.srcs.wait_parse_ready(fid, true);
+
+        // prepare from a handle of the class
+        let driver = server
+            .prepare_type_hierarchy(TypeHierarchyPrepareParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position: Position::new(17, 10),
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            })
+            .unwrap()
+            .remove(0);
+        assert_eq!(driver.name, "driver");
+        let supertypes = server
+            .supertypes(TypeHierarchySupertypesParams {
+                item: driver,
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .unwrap();
+        let names: Vec<&str> = supertypes.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, vec!["component", "resettable"]);
+        assert_eq!(supertypes[0].selection_range.start, Position::new(4, 16));
+
+        let subtypes = server
+            .subtypes(TypeHierarchySubtypesParams {
+                item: supertypes[0].clone(),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .unwrap();
+        let names: Vec<&str> = subtypes.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, vec!["driver", "monitor"]);
+    }
+}
@@ -0,0 +1,6 @@
+`include "macros.svh"
+`define PAIR(a, b) `REG(a) `REG(b, 4)
+module macro_test;
+  `PAIR(x, y)
+  `REG(z)
+endmodule